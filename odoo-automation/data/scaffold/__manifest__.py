@@ -0,0 +1,13 @@
+{
+    'name': "{{title}}",
+    'version': '{{version}}',
+    'summary': "",
+    'category': 'Uncategorized',
+    'license': 'LGPL-3',
+    'depends': ['base'],
+    'data': [
+        'security/ir.model.access.csv',{{data}}
+    ],
+    'installable': True,
+    'application': False,
+}
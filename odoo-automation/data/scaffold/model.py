@@ -0,0 +1,10 @@
+from odoo import fields, models
+
+
+class {{class}}(models.Model):
+    _name = '{{model}}'
+    _description = "{{title}} Record"
+
+    name = fields.Char(required=True)
+    description = fields.Text()
+    active = fields.Boolean(default=True)
@@ -0,0 +1,517 @@
+use crate::git;
+use crate::lockfile::{self, LockedAddon, Lockfile};
+use crate::manifest::Manifest;
+use crate::plan::{Action, Plan};
+use crate::sources::Source;
+use dialoguer::MultiSelect;
+use std::collections::BTreeMap;
+use std::fs::{self, read_dir};
+use std::path::{Path, PathBuf};
+
+// The addons available from every source, for one Odoo version
+pub struct Pack {
+    // Top-level entries offered in the wizard: single addons or groups
+    pub entries: Vec<PackEntry>,
+    // Every addon by technical name
+    pub addons: BTreeMap<String, PackAddon>,
+    // Sources a dry run read in another state than a sync would leave them
+    pub unsynced: Vec<String>,
+}
+
+pub struct PackEntry {
+    pub name: String,
+    pub source: String,
+    pub path: PathBuf,
+}
+
+// An addon found in a source, either at the top level or inside a group
+pub struct PackAddon {
+    pub path: PathBuf,
+    pub source: String,
+    // Git URL or local directory of the source, and its checked out commit
+    pub repo: String,
+    pub commit: Option<String>,
+    pub manifest: Result<Manifest, String>,
+}
+
+impl Pack {
+    // Sync every source and index its addons. When several sources provide
+    // the same addon, the first source in the list wins. A dry run reads the
+    // sources as they are on disk and lists the ones that aren't synced.
+    pub fn load(
+        sources: &[Source],
+        odoo_version: &str,
+        pull: Option<bool>,
+        dry_run: bool,
+    ) -> Result<Pack, String> {
+        let mut pack = Pack {
+            entries: Vec::new(),
+            addons: BTreeMap::new(),
+            unsynced: Vec::new(),
+        };
+        for source in sources {
+            let repo_path = if dry_run {
+                if let Err(e) = source.check_synced(odoo_version) {
+                    pack.unsynced.push(e);
+                }
+                let path = source.local_path();
+                if !path.is_dir() {
+                    continue;
+                }
+                path
+            } else {
+                source.sync(odoo_version, pull)?
+            };
+            let repo = source
+                .git
+                .clone()
+                .unwrap_or_else(|| repo_path.to_string_lossy().into_owned());
+            let commit = git::head_commit(&repo_path);
+            for name in list_addons(&repo_path.to_string_lossy()) {
+                if pack.entry(&name).is_some() {
+                    eprintln!(
+                        "Warning: {} from '{}' is hidden by another source",
+                        name, source.name
+                    );
+                    continue;
+                }
+                let path = repo_path.join(&name);
+                for addon_path in entry_addon_dirs(&path) {
+                    let addon_name = addon_path
+                        .file_name()
+                        .unwrap()
+                        .to_string_lossy()
+                        .into_owned();
+                    pack.addons.entry(addon_name).or_insert_with(|| PackAddon {
+                        manifest: Manifest::load(&addon_path),
+                        path: addon_path,
+                        source: source.name.clone(),
+                        repo: repo.clone(),
+                        commit: commit.clone(),
+                    });
+                }
+                pack.entries.push(PackEntry {
+                    name,
+                    source: source.name.clone(),
+                    path,
+                });
+            }
+        }
+        Ok(pack)
+    }
+
+    fn entry(&self, name: &str) -> Option<&PackEntry> {
+        self.entries.iter().find(|entry| entry.name == name)
+    }
+}
+
+// List all addons (or groups of addons) in the repository
+pub fn list_addons(repo_path: &str) -> Vec<String> {
+    let mut addons: Vec<String> = read_dir(repo_path)
+        .expect("Failed to read repository directory")
+        .filter_map(|entry| {
+            let entry = entry.expect("Failed to read entry");
+            let path = entry.path();
+            let name = path.file_name().unwrap().to_string_lossy().into_owned();
+            if path.is_dir() && !name.starts_with('.') {
+                Some(name)
+            } else {
+                None
+            }
+        })
+        .collect();
+    addons.sort();
+    addons
+}
+
+// Multi-select prompt for addons, unless they were given up front
+pub fn choose_addons(pack: &Pack, wanted: Option<&[String]>) -> Result<Vec<String>, String> {
+    match wanted {
+        Some(wanted) => wanted
+            .iter()
+            .map(|name| match pack.entry(name) {
+                Some(_) => Ok(name.clone()),
+                None => Err(format!("Addon '{}' not found in the addon sources", name)),
+            })
+            .collect(),
+        None => {
+            let items: Vec<String> = pack
+                .entries
+                .iter()
+                .map(|entry| format!("{} [{}]", entry.name, entry.source))
+                .collect();
+            let selected: Vec<usize> = MultiSelect::new()
+                .with_prompt("Select addons to include in your project")
+                .items(&items)
+                .interact()
+                .expect("Failed to select addons");
+            Ok(selected
+                .into_iter()
+                .map(|i| pack.entries[i].name.clone())
+                .collect())
+        }
+    }
+}
+
+// The addon directories behind a pack entry: the entry itself when it has a
+// __manifest__.py, otherwise the addons of the group
+fn entry_addon_dirs(entry_path: &Path) -> Vec<PathBuf> {
+    if entry_path.join("__manifest__.py").exists() {
+        return vec![entry_path.to_path_buf()];
+    }
+    let mut dirs: Vec<PathBuf> = fs::read_dir(entry_path)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.join("__manifest__.py").exists())
+                .collect()
+        })
+        .unwrap_or_default();
+    dirs.sort();
+    dirs
+}
+
+// Expand the selected pack entries into the addons to copy, pulling in the
+// dependencies found in the sources. Addons already in the project are skipped.
+pub fn resolve_addons(
+    pack: &Pack,
+    selected: &[String],
+    odoo_version: &str,
+    installed: &[String],
+) -> Result<Vec<String>, String> {
+    let mut resolved: Vec<String> = Vec::new();
+    let mut missing: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+    // (addon, required): required addons must be usable, while unusable
+    // members of a selected group are only skipped
+    let mut queue: Vec<(String, bool)> = Vec::new();
+    for name in selected {
+        let entry = pack.entry(name).unwrap();
+        let is_group = !entry.path.join("__manifest__.py").exists();
+        for path in entry_addon_dirs(&entry.path) {
+            let name = path.file_name().unwrap().to_string_lossy().into_owned();
+            queue.push((name, !is_group));
+        }
+    }
+    queue.reverse();
+
+    while let Some((name, required)) = queue.pop() {
+        if resolved.contains(&name) || installed.contains(&name) {
+            continue;
+        }
+        let addon = &pack.addons[&name];
+        let problem = match &addon.manifest {
+            Err(e) => Some(e.clone()),
+            Ok(manifest) if !manifest.installable => Some(format!("{} is not installable", name)),
+            Ok(manifest) if !manifest.supports(odoo_version) => Some(format!(
+                "{} {} is not made for Odoo {}",
+                name,
+                manifest.version.as_deref().unwrap_or_default(),
+                odoo_version
+            )),
+            Ok(_) => None,
+        };
+        if let Some(problem) = problem {
+            if required {
+                return Err(problem);
+            }
+            eprintln!("Warning: skipping {}", problem);
+            continue;
+        }
+
+        resolved.push(name.clone());
+        let manifest = addon.manifest.as_ref().unwrap();
+        for dependency in manifest.depends.iter().rev() {
+            if pack.addons.contains_key(dependency) {
+                if !resolved.contains(dependency) && !installed.contains(dependency) {
+                    println!("Adding {} (required by {})", dependency, name);
+                }
+                queue.push((dependency.clone(), true));
+            } else {
+                missing
+                    .entry(name.clone())
+                    .or_default()
+                    .push(dependency.clone());
+            }
+        }
+    }
+
+    for (name, dependencies) in missing {
+        eprintln!(
+            "Warning: {} depends on {}, not found in the addon sources (fine for Odoo core modules)",
+            name,
+            dependencies.join(", ")
+        );
+    }
+    Ok(resolved)
+}
+
+// Actions copying the resolved addons to the project's custom_addons folder
+pub fn copy_actions(pack: &Pack, addons: &[String], project_dir: &Path) -> Vec<Action> {
+    let dest = project_dir.join("custom_addons"); // Destination is always custom_addons
+    addons
+        .iter()
+        .map(|addon_name| Action::CopyDir {
+            from: pack.addons[addon_name].path.clone(),
+            to: dest.join(addon_name),
+        })
+        .collect()
+}
+
+// Copy the resolved addons to the project's custom_addons folder
+pub fn copy_addons(pack: &Pack, addons: &[String], project_dir: &Path) -> Result<(), String> {
+    let plan = Plan {
+        actions: copy_actions(pack, addons, project_dir),
+    };
+    plan.execute().map_err(|e| e.to_string())?;
+    report_copied(pack, addons);
+
+    let mut lock = Lockfile::load(project_dir)?;
+    for addon_name in addons {
+        lock.record(lock_entry(pack, addon_name)?);
+    }
+    lock.save(project_dir)
+}
+
+// Lockfile entry of an addon copied as it is in the source
+pub fn lock_entry(pack: &Pack, addon_name: &str) -> Result<LockedAddon, String> {
+    let addon = &pack.addons[addon_name];
+    let checksum = lockfile::checksum(&addon.path)
+        .map_err(|e| format!("Failed to read {}: {}", addon.path.display(), e))?;
+    Ok(LockedAddon {
+        name: addon_name.to_string(),
+        source: addon.source.clone(),
+        repo: addon.repo.clone(),
+        commit: addon.commit.clone(),
+        version: addon
+            .manifest
+            .as_ref()
+            .ok()
+            .and_then(|manifest| manifest.version.clone()),
+        checksum,
+    })
+}
+
+// How a locked addon compares to the source and to what was copied
+pub struct AddonStatus {
+    pub locked: LockedAddon,
+    // Lock entry for the addon as it is now in the source, None when the
+    // source no longer has it
+    pub latest: Option<LockedAddon>,
+    pub locally_modified: bool,
+    // Files that an update would add, modify or delete
+    pub changes: Vec<String>,
+}
+
+impl AddonStatus {
+    pub fn outdated(&self) -> bool {
+        matches!(&self.latest, Some(latest) if latest.checksum != self.locked.checksum)
+    }
+
+    // e.g. "sale_extra 17.0.1.0.0 -> 17.0.1.1.0 (1a2b3c4 -> 5d6e7f8)"
+    pub fn describe(&self) -> String {
+        let version =
+            |addon: &LockedAddon| addon.version.clone().unwrap_or_else(|| "?".to_string());
+        let commit = |addon: &LockedAddon| match &addon.commit {
+            Some(commit) => commit[..commit.len().min(7)].to_string(),
+            None => "local".to_string(),
+        };
+        let mut text = match &self.latest {
+            None => format!(
+                "{} {} (not found in the addon sources)",
+                self.locked.name,
+                version(&self.locked)
+            ),
+            Some(latest) => format!(
+                "{} {} -> {} ({} -> {})",
+                self.locked.name,
+                version(&self.locked),
+                version(latest),
+                commit(&self.locked),
+                commit(latest)
+            ),
+        };
+        if self.locally_modified {
+            text += ", modified locally";
+        }
+        text
+    }
+}
+
+// Compare every locked addon of the project with the addon sources
+pub fn check_locked(pack: &Pack, project_dir: &Path) -> Result<Vec<AddonStatus>, String> {
+    let lock = Lockfile::load(project_dir)?;
+    let mut statuses = Vec::new();
+    for locked in lock.addons {
+        let copy = project_dir.join("custom_addons").join(&locked.name);
+        if !copy.is_dir() {
+            continue;
+        }
+        let read_error =
+            |path: &Path, e: std::io::Error| format!("Failed to read {}: {}", path.display(), e);
+        let local = lockfile::checksum(&copy).map_err(|e| read_error(&copy, e))?;
+        let (latest, changes) = match pack.addons.get(&locked.name) {
+            Some(addon) => (
+                Some(lock_entry(pack, &locked.name)?),
+                lockfile::diff(&copy, &addon.path).map_err(|e| read_error(&copy, e))?,
+            ),
+            None => (None, Vec::new()),
+        };
+        statuses.push(AddonStatus {
+            locally_modified: local != locked.checksum,
+            locked,
+            latest,
+            changes,
+        });
+    }
+    Ok(statuses)
+}
+
+// Replace the project's copy of an addon with the one in the source. The old
+// copy is put back if the new one cannot be copied.
+pub fn update_addon(pack: &Pack, addon_name: &str, project_dir: &Path) -> Result<(), String> {
+    let addon = &pack.addons[addon_name];
+    let copy = project_dir.join("custom_addons").join(addon_name);
+    let old = copy.with_file_name(format!(".{}.old-{}", addon_name, std::process::id()));
+    fs::rename(&copy, &old).map_err(|e| format!("Failed to move {}: {}", copy.display(), e))?;
+
+    let plan = Plan {
+        actions: vec![Action::CopyDir {
+            from: addon.path.clone(),
+            to: copy.clone(),
+        }],
+    };
+    if let Err(e) = plan.execute_into(&copy) {
+        let _ = fs::rename(&old, &copy);
+        return Err(format!("{}, {} was left unchanged", e, addon_name));
+    }
+    let _ = fs::remove_dir_all(&old);
+
+    let mut lock = Lockfile::load(project_dir)?;
+    lock.record(lock_entry(pack, addon_name)?);
+    lock.save(project_dir)
+}
+
+// Tell where each copied addon came from and what it needs to run
+pub fn report_copied(pack: &Pack, addons: &[String]) {
+    for addon_name in addons {
+        let addon = &pack.addons[addon_name];
+        if let Ok(manifest) = &addon.manifest {
+            println!(
+                "Copied addon: {} ({}) from {}",
+                addon_name, manifest.name, addon.source
+            );
+            let external = &manifest.external_dependencies;
+            if !external.python.is_empty() || !external.bin.is_empty() {
+                println!(
+                    "  {} needs external dependencies: {}",
+                    addon_name,
+                    [external.python.as_slice(), external.bin.as_slice()]
+                        .concat()
+                        .join(", ")
+                );
+            }
+        }
+    }
+}
+
+// Addon directories currently in the project's custom_addons folder
+pub fn installed_addons(project_dir: &Path) -> Vec<String> {
+    let custom_addons = project_dir.join("custom_addons");
+    if !custom_addons.is_dir() {
+        return Vec::new();
+    }
+    list_addons(&custom_addons.to_string_lossy())
+}
+
+// Helper function to copy all contents of a directory
+pub fn copy_dir_contents(src: &str, dst: &str) -> std::io::Result<()> {
+    for entry in fs::read_dir(src)? {
+        let entry = entry?; // Handle potential errors in reading the entry
+        let entry_path = entry.path(); // Get the full path of the entry
+        let entry_name = entry.file_name().to_string_lossy().into_owned(); // Get the name of the entry
+
+        // Create the destination path
+        let dest_path = format!("{}/{}", dst, entry_name);
+
+        // If it's a directory, copy it recursively
+        if entry_path.is_dir() {
+            fs::create_dir_all(&dest_path)?;
+            copy_dir_contents(entry_path.to_str().unwrap(), &dest_path)?;
+        } else {
+            // If it's a file, copy it directly
+            fs::copy(&entry_path, &dest_path)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A local source with `sale_extra` -> `base_extra` -> `base_tools`, a
+    // `reporting` group holding a non-installable addon, and `needs_old`
+    // depending on a non-installable addon
+    fn pack(dir: &Path) -> Pack {
+        let addon = |path: &str, manifest: &str| {
+            fs::create_dir_all(dir.join(path)).unwrap();
+            fs::write(dir.join(path).join("__manifest__.py"), manifest).unwrap();
+        };
+        addon("sale_extra", "{'depends': ['sale', 'base_extra']}");
+        addon("base_extra", "{'depends': ['base_tools']}");
+        addon("base_tools", "{'depends': ['base']}");
+        addon("old_addon", "{'installable': False}");
+        addon("needs_old", "{'depends': ['old_addon']}");
+        addon("reporting/report_a", "{'version': '17.0.1.0.0'}");
+        addon("reporting/report_old", "{'version': '16.0.1.0.0'}");
+
+        let source = Source {
+            name: "local".to_string(),
+            git: None,
+            path: Some(dir.to_string_lossy().into_owned()),
+            branch: None,
+            checkout: None,
+        };
+        Pack::load(&[source], "17", Some(false), false).unwrap()
+    }
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn resolves_dependencies() {
+        let dir =
+            std::env::temp_dir().join(format!("odoo-automation-resolve-{}", std::process::id()));
+        let pack = pack(&dir);
+
+        // Transitive dependencies found in the sources
+        assert_eq!(
+            resolve_addons(&pack, &names(&["sale_extra"]), "17", &[]).unwrap(),
+            names(&["sale_extra", "base_extra", "base_tools"])
+        );
+        // Addons already in the project are left alone
+        assert_eq!(
+            resolve_addons(
+                &pack,
+                &names(&["sale_extra"]),
+                "17",
+                &names(&["base_tools"])
+            )
+            .unwrap(),
+            names(&["sale_extra", "base_extra"])
+        );
+        // Unusable group members are skipped, unusable required addons abort
+        assert_eq!(
+            resolve_addons(&pack, &names(&["reporting"]), "17", &[]).unwrap(),
+            names(&["report_a"])
+        );
+        assert_eq!(
+            resolve_addons(&pack, &names(&["needs_old"]), "17", &[]),
+            Err("old_addon is not installable".to_string())
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
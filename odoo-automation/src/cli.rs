@@ -0,0 +1,381 @@
+use crate::odoo_conf::Preset;
+use crate::project::Edition;
+use crate::proxy::Proxy;
+use crate::spec::ProjectSpec;
+use clap::builder::BoolishValueParser;
+use clap::{Args, Parser, Subcommand};
+use std::path::PathBuf;
+
+#[derive(Parser)]
+#[command(
+    name = "odoo-automation",
+    about = "Generate a docker-compose based Odoo project",
+    args_conflicts_with_subcommands = true
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Commands>,
+
+    // Without a subcommand, run the project wizard
+    #[command(flatten)]
+    pub new: NewArgs,
+}
+
+#[derive(Subcommand)]
+pub enum Commands {
+    /// Create a new project (the default when no command is given)
+    New(NewArgs),
+
+    /// Copy addons from the addon sources into a project
+    AddAddon {
+        #[command(flatten)]
+        project: ProjectDir,
+
+        /// Addons to copy; asked interactively when omitted
+        addons: Vec<String>,
+
+        /// Pull the latest changes of the git addon sources (yes/no)
+        #[arg(long, value_name = "YES/NO", value_parser = BoolishValueParser::new(), hide_possible_values = true)]
+        pull_addons: Option<bool>,
+    },
+
+    /// Remove addons from a project's custom_addons folder
+    RemoveAddon {
+        #[command(flatten)]
+        project: ProjectDir,
+
+        /// Addons to remove; asked interactively when omitted
+        addons: Vec<String>,
+    },
+
+    /// Start the project's containers and wait for Odoo to answer
+    Start {
+        #[command(flatten)]
+        project: ProjectDir,
+
+        /// Seconds to wait for PostgreSQL and Odoo to be ready
+        #[arg(long, value_name = "SECONDS", default_value_t = 180)]
+        timeout: u64,
+    },
+
+    /// Stop the project's containers
+    Stop {
+        #[command(flatten)]
+        project: ProjectDir,
+    },
+
+    /// Follow the logs of the project's containers
+    Logs {
+        #[command(flatten)]
+        project: ProjectDir,
+
+        /// Only show the logs of this service (e.g. web or postgres)
+        service: Option<String>,
+
+        /// Number of lines to show before following
+        #[arg(long, default_value_t = 100)]
+        tail: u32,
+    },
+
+    /// Show the status of the project's containers
+    Status {
+        #[command(flatten)]
+        project: ProjectDir,
+    },
+
+    /// List the projects created by this tool
+    List,
+
+    /// Remove a project from the list, leaving its files untouched
+    Forget {
+        #[command(flatten)]
+        project: ProjectDir,
+    },
+
+    /// Stop a project's containers and delete it with all its data
+    Destroy {
+        #[command(flatten)]
+        project: ProjectDir,
+
+        /// Don't ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+
+    /// Generate new database and master passwords for an existing project
+    RotateSecrets {
+        #[command(flatten)]
+        project: ProjectDir,
+    },
+
+    /// Work on the addons of a project
+    #[command(visible_alias = "addons")]
+    Addon {
+        #[command(subcommand)]
+        command: AddonCommand,
+    },
+
+    /// Create, back up, restore and drop the project's databases
+    Db {
+        #[command(subcommand)]
+        command: DbCommand,
+    },
+
+    /// List the saved project templates
+    Templates,
+}
+
+#[derive(Subcommand)]
+pub enum AddonCommand {
+    /// Generate the skeleton of a new addon in custom_addons
+    New {
+        #[command(flatten)]
+        project: ProjectDir,
+
+        /// Technical name of the addon, e.g. sale_extra
+        name: String,
+
+        /// Include a sample model with its views, access rights and menu
+        #[arg(long)]
+        sample: bool,
+    },
+
+    /// Show the copied addons that changed in the addon sources
+    Outdated {
+        #[command(flatten)]
+        project: ProjectDir,
+
+        /// Pull the latest changes of the git addon sources (yes/no)
+        #[arg(long, value_name = "YES/NO", value_parser = BoolishValueParser::new(), hide_possible_values = true)]
+        pull_addons: Option<bool>,
+    },
+
+    /// Replace copied addons with their current version from the addon sources
+    Update {
+        #[command(flatten)]
+        project: ProjectDir,
+
+        /// Addons to update (asked for if omitted)
+        addons: Vec<String>,
+
+        /// Pull the latest changes of the git addon sources (yes/no)
+        #[arg(long, value_name = "YES/NO", value_parser = BoolishValueParser::new(), hide_possible_values = true)]
+        pull_addons: Option<bool>,
+
+        /// Overwrite local modifications without asking
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum DbCommand {
+    /// Create a database with the base module installed
+    Create {
+        #[command(flatten)]
+        project: ProjectDir,
+
+        /// Database name
+        name: String,
+
+        /// Language to load, e.g. fr_FR
+        #[arg(long)]
+        lang: Option<String>,
+
+        /// Load the demo data
+        #[arg(long)]
+        demo: bool,
+    },
+
+    /// Delete a database and its filestore
+    Drop {
+        #[command(flatten)]
+        project: ProjectDir,
+
+        /// Database name
+        name: String,
+
+        /// Don't ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+
+    /// Save a database and its filestore to a zip file
+    Backup {
+        #[command(flatten)]
+        project: ProjectDir,
+
+        /// Database name
+        name: String,
+
+        /// Zip file to write (default: <name>_<date>.zip)
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+
+    /// Restore a zip backup into a new database
+    Restore {
+        #[command(flatten)]
+        project: ProjectDir,
+
+        /// Name of the new database
+        name: String,
+
+        /// Zip file made by `db backup` or by Odoo's database manager
+        file: PathBuf,
+    },
+}
+
+#[derive(Args)]
+pub struct ProjectDir {
+    /// Project directory
+    #[arg(short, long = "project", value_name = "DIR", default_value = ".")]
+    pub path: PathBuf,
+}
+
+// Wizard flags; anything left out is asked interactively
+#[derive(Args, Default)]
+pub struct NewArgs {
+    /// Start from the answers of a saved template
+    #[arg(long, value_name = "NAME")]
+    pub template: Option<String>,
+
+    /// Save the answers of this run as a template
+    #[arg(long, value_name = "NAME")]
+    pub save_template: Option<String>,
+
+    /// Read the project answers from a YAML spec file
+    #[arg(long, value_name = "FILE")]
+    pub spec: Option<String>,
+
+    /// Project name (also used as the project directory)
+    #[arg(long)]
+    pub name: Option<String>,
+
+    /// Odoo version, e.g. 17 or 17.0
+    #[arg(long, value_name = "VERSION")]
+    pub odoo_version: Option<String>,
+
+    /// PostgreSQL version, e.g. 16 or 16.4 (defaults to the one recommended for the Odoo version)
+    #[arg(long, value_name = "VERSION")]
+    pub postgres_version: Option<String>,
+
+    /// Host port for the Odoo web interface
+    #[arg(long)]
+    pub port: Option<u16>,
+
+    /// Server settings preset written to odoo.conf
+    #[arg(long, value_enum)]
+    pub preset: Option<Preset>,
+
+    /// Odoo edition
+    #[arg(long, value_enum)]
+    pub edition: Option<Edition>,
+
+    /// Local checkout of the Odoo Enterprise addons, for the enterprise edition
+    #[arg(long, value_name = "DIR")]
+    pub enterprise_path: Option<String>,
+
+    /// Reverse proxy serving Odoo over HTTPS with a self-signed certificate
+    #[arg(long, value_enum)]
+    pub proxy: Option<Proxy>,
+
+    /// Domain name served by the proxy (default: <name>.localhost)
+    #[arg(long)]
+    pub domain: Option<String>,
+
+    /// Comma-separated addons to copy from the addon sources (pass it empty to skip addons)
+    #[arg(long, value_delimiter = ',', num_args = 0..)]
+    pub addons: Option<Vec<String>>,
+
+    /// Pull the latest changes of the git addon sources (yes/no)
+    #[arg(long, value_name = "YES/NO", value_parser = BoolishValueParser::new(), hide_possible_values = true)]
+    pub pull_addons: Option<bool>,
+
+    /// Comma-separated companion services, e.g. mailpit,pgadmin (pass it empty for none)
+    #[arg(long, value_delimiter = ',', num_args = 0..)]
+    pub services: Option<Vec<String>>,
+
+    /// Start docker compose once the project is created (yes/no)
+    #[arg(long, value_name = "YES/NO", value_parser = BoolishValueParser::new(), hide_possible_values = true)]
+    pub start_docker: Option<bool>,
+
+    /// Make the folders mounted in the containers writable by every user
+    /// instead of giving them to the Odoo container user
+    #[arg(long)]
+    pub world_writable: bool,
+
+    /// Show what would be created and ask before doing it
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Update an existing project directory, showing what changes
+    #[arg(long)]
+    pub force: bool,
+}
+
+impl NewArgs {
+    // The answers given on the command line, in spec form
+    pub fn answers(&self) -> ProjectSpec {
+        ProjectSpec {
+            name: self.name.clone(),
+            odoo_version: self.odoo_version.clone(),
+            postgres_version: self.postgres_version.clone(),
+            port: self.port,
+            preset: self.preset,
+            edition: self.edition,
+            enterprise_path: self.enterprise_path.clone(),
+            proxy: self.proxy,
+            domain: self.domain.clone(),
+            addons: non_empty(&self.addons),
+            services: non_empty(&self.services),
+            pull_addons: self.pull_addons,
+            start_docker: self.start_docker,
+            world_writable: self.world_writable.then_some(true),
+            ..Default::default()
+        }
+    }
+}
+
+// `--addons=` or `--services=` give one empty item: drop it so the flag
+// means none
+fn non_empty(items: &Option<Vec<String>>) -> Option<Vec<String>> {
+    items.as_ref().map(|items| {
+        items
+            .iter()
+            .filter(|item| !item.is_empty())
+            .cloned()
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn answers(args: &[&str]) -> ProjectSpec {
+        let cli = Cli::try_parse_from([&["odoo-automation"], args].concat()).unwrap();
+        cli.new.answers()
+    }
+
+    #[test]
+    fn empty_addons_mean_none() {
+        for args in [&["--addons="][..], &["--addons", ""], &["--addons"]] {
+            assert_eq!(answers(args).addons, Some(Vec::new()), "{:?}", args);
+        }
+        assert_eq!(
+            answers(&["--addons=sale_extra,,base_tools"]).addons,
+            Some(vec!["sale_extra".to_string(), "base_tools".to_string()])
+        );
+        assert_eq!(answers(&[]).addons, None);
+    }
+
+    #[test]
+    fn empty_services_mean_none() {
+        assert_eq!(answers(&["--services="]).services, Some(Vec::new()));
+        assert_eq!(
+            answers(&["--services", "mailpit,"]).services,
+            Some(vec!["mailpit".to_string()])
+        );
+    }
+}
@@ -0,0 +1,161 @@
+use crate::ports::{HTTP_PORT, LONGPOLLING_PORT};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+// Typed model of a docker-compose.yml, serialized with serde_yaml
+#[derive(Debug, Default, Serialize)]
+pub struct ComposeFile {
+    pub services: BTreeMap<String, Service>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub volumes: BTreeMap<String, Volume>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub networks: BTreeMap<String, Network>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct Service {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    // Build context of an image made for the project
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub build: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub command: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub ports: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub volumes: Vec<String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub depends_on: BTreeMap<String, DependsOn>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub environment: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub healthcheck: Option<HealthCheck>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub networks: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub labels: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub restart: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DependsOn {
+    pub condition: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HealthCheck {
+    pub test: Vec<String>,
+    pub interval: String,
+    pub timeout: String,
+    pub retries: u32,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct Volume {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub driver: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct Network {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub driver: Option<String>,
+}
+
+impl ComposeFile {
+    pub fn to_yaml(&self) -> Result<String, serde_yaml::Error> {
+        serde_yaml::to_string(self)
+    }
+}
+
+// Where the enterprise addons are mounted in the Odoo container
+pub const ENTERPRISE_ADDONS: &str = "/mnt/enterprise-addons";
+
+// Name of the shared network and of the database service
+pub const NETWORK: &str = "odoo";
+pub const DB_SERVICE: &str = "postgres";
+
+// The default stack: Odoo on `port` (and `longpolling_port` for the
+// longpolling/websocket worker), backed by a PostgreSQL service. The
+// enterprise addons checkout, if any, is mounted read-only.
+// Passwords are interpolated by docker compose from the project's .env file.
+pub fn odoo_stack(
+    odoo_image: &str,
+    postgres_version: &str,
+    port: u16,
+    longpolling_port: u16,
+    enterprise_path: Option<&Path>,
+) -> ComposeFile {
+    let mut compose = ComposeFile::default();
+
+    let mut web = Service {
+        image: Some(odoo_image.to_string()),
+        ports: vec![
+            format!("{}:{}", port, HTTP_PORT),
+            format!("{}:{}", longpolling_port, LONGPOLLING_PORT),
+        ],
+        volumes: vec![
+            "./config:/etc/odoo".to_string(),
+            "./addons:/mnt/extra-addons".to_string(),
+            "./custom_addons:/mnt/custom-addons".to_string(),
+            "./data:/var/lib/odoo".to_string(),
+        ],
+        depends_on: BTreeMap::from([(
+            DB_SERVICE.to_string(),
+            DependsOn {
+                condition: "service_healthy".to_string(),
+            },
+        )]),
+        environment: env(&[
+            ("HOST", DB_SERVICE),
+            ("USER", "odoo"),
+            ("PASSWORD", "${DB_PASSWORD}"),
+        ]),
+        networks: vec![NETWORK.to_string()],
+        restart: Some("always".to_string()),
+        ..Default::default()
+    };
+
+    if let Some(path) = enterprise_path {
+        web.volumes
+            .insert(0, format!("{}:{}:ro", path.display(), ENTERPRISE_ADDONS));
+    }
+
+    let postgres = Service {
+        image: Some(format!("postgres:{}", postgres_version)),
+        volumes: vec!["./data/pgdata:/var/lib/postgresql/data".to_string()],
+        environment: env(&[
+            ("POSTGRES_DB", "postgres"),
+            ("POSTGRES_PASSWORD", "${DB_PASSWORD}"),
+            ("POSTGRES_USER", "odoo"),
+        ]),
+        healthcheck: Some(HealthCheck {
+            test: vec![
+                "CMD-SHELL".to_string(),
+                "pg_isready -U odoo -d postgres".to_string(),
+            ],
+            interval: "10s".to_string(),
+            timeout: "5s".to_string(),
+            retries: 5,
+        }),
+        networks: vec![NETWORK.to_string()],
+        restart: Some("always".to_string()),
+        ..Default::default()
+    };
+
+    compose.services.insert("web".to_string(), web);
+    compose.services.insert(DB_SERVICE.to_string(), postgres);
+    compose
+        .networks
+        .insert(NETWORK.to_string(), Network::default());
+    compose
+}
+
+fn env(vars: &[(&str, &str)]) -> BTreeMap<String, String> {
+    vars.iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
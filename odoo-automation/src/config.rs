@@ -0,0 +1,73 @@
+use crate::sources::Source;
+use serde::Deserialize;
+use std::fs;
+use std::path::PathBuf;
+
+// The tool's own settings, read from config.yml in the config directory
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    // Where addons are taken from, in priority order
+    pub sources: Vec<Source>,
+    // User and group the official Odoo image runs as, given the folders
+    // the container writes to
+    pub odoo_uid: u32,
+    pub odoo_gid: u32,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            sources: vec![Source::bbg_pack()],
+            odoo_uid: 101,
+            odoo_gid: 101,
+        }
+    }
+}
+
+impl Config {
+    pub fn load() -> Result<Config, String> {
+        let path = config_dir().join("config.yml");
+        let config: Config = match fs::read_to_string(&path) {
+            Ok(content) => serde_yaml::from_str(&content)
+                .map_err(|e| format!("Invalid {}: {}", path.display(), e))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Config::default(),
+            Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+        };
+        for source in &config.sources {
+            source
+                .validate()
+                .map_err(|e| format!("Invalid source in {}: {}", path.display(), e))?;
+        }
+        Ok(config)
+    }
+}
+
+// Directory of the tool's own configuration files
+pub fn config_dir() -> PathBuf {
+    xdg_dir("XDG_CONFIG_HOME", ".config")
+}
+
+// Directory for checkouts of the git addon sources
+pub fn cache_dir() -> PathBuf {
+    xdg_dir("XDG_CACHE_HOME", ".cache")
+}
+
+fn xdg_dir(variable: &str, fallback: &str) -> PathBuf {
+    match std::env::var(variable) {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir).join("odoo-automation"),
+        _ => home_dir().join(fallback).join("odoo-automation"),
+    }
+}
+
+pub fn home_dir() -> PathBuf {
+    PathBuf::from(std::env::var("HOME").unwrap())
+}
+
+// Expand a leading `~/` to the home directory
+pub fn expand_home(path: &str) -> PathBuf {
+    match path.strip_prefix("~/") {
+        Some(rest) => home_dir().join(rest),
+        None => PathBuf::from(path),
+    }
+}
@@ -0,0 +1,415 @@
+use crate::docker;
+use crate::project::Project;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use zip::write::SimpleFileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+// Where Odoo keeps the attachments of every database, inside the web container
+const FILESTORE: &str = "/var/lib/odoo/filestore";
+
+// Database names Odoo itself accepts
+pub fn validate_name(name: &str) -> Result<(), String> {
+    let valid = name.starts_with(|c: char| c.is_ascii_alphanumeric())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+        && !name.contains("..");
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "Invalid database name '{}': use letters, digits, '_', '-' and '.'",
+            name
+        ))
+    }
+}
+
+// The `docker compose` arguments of every step, kept apart from running them
+
+// Initialize a database with the base module, using the project's odoo.conf
+pub fn create_args(name: &str, lang: Option<&str>, demo: bool) -> Vec<String> {
+    let mut args = strings(&[
+        "exec",
+        "-T",
+        "web",
+        "odoo",
+        "-d",
+        name,
+        "-i",
+        "base",
+        "--stop-after-init",
+        "--no-http",
+    ]);
+    if !demo {
+        args.push("--without-demo=all".to_string());
+    }
+    if let Some(lang) = lang {
+        args.push(format!("--load-language={}", lang));
+    }
+    args
+}
+
+// Drop a database, closing the connections of a running Odoo when the
+// server supports it (PostgreSQL 13 and later)
+pub fn drop_args(name: &str, postgres_version: &str) -> Vec<String> {
+    let mut args = strings(&["exec", "-T", "postgres", "dropdb", "-U", "odoo"]);
+    let major: u32 = postgres_version
+        .split('.')
+        .next()
+        .and_then(|major| major.parse().ok())
+        .unwrap_or(0);
+    if major >= 13 {
+        args.push("--force".to_string());
+    }
+    args.push(name.to_string());
+    args
+}
+
+pub fn remove_filestore_args(name: &str) -> Vec<String> {
+    strings(&["exec", "-T", "web", "rm", "-rf", &filestore(name)])
+}
+
+pub fn has_filestore_args(name: &str) -> Vec<String> {
+    strings(&["exec", "-T", "web", "test", "-d", &filestore(name)])
+}
+
+// SQL dump of a database on stdout, in the format Odoo's backups use
+pub fn dump_args(name: &str) -> Vec<String> {
+    strings(&[
+        "exec",
+        "-T",
+        "postgres",
+        "pg_dump",
+        "-U",
+        "odoo",
+        "--no-owner",
+        name,
+    ])
+}
+
+pub fn create_empty_args(name: &str) -> Vec<String> {
+    strings(&["exec", "-T", "postgres", "createdb", "-U", "odoo", name])
+}
+
+// Load an SQL dump read from stdin
+pub fn load_dump_args(name: &str) -> Vec<String> {
+    strings(&[
+        "exec",
+        "-T",
+        "postgres",
+        "psql",
+        "-U",
+        "odoo",
+        "-d",
+        name,
+        "-q",
+        "-v",
+        "ON_ERROR_STOP=1",
+    ])
+}
+
+pub fn copy_filestore_out_args(name: &str, to: &Path) -> Vec<String> {
+    vec![
+        "cp".to_string(),
+        format!("web:{}", filestore(name)),
+        to.display().to_string(),
+    ]
+}
+
+pub fn copy_filestore_in_args(name: &str, from: &Path) -> Vec<String> {
+    vec![
+        "cp".to_string(),
+        from.display().to_string(),
+        format!("web:{}", filestore(name)),
+    ]
+}
+
+pub fn prepare_filestore_args() -> Vec<String> {
+    strings(&["exec", "-T", "web", "mkdir", "-p", FILESTORE])
+}
+
+// `docker compose cp` writes the files as root
+pub fn own_filestore_args(name: &str) -> Vec<String> {
+    strings(&[
+        "exec",
+        "-T",
+        "-u",
+        "root",
+        "web",
+        "chown",
+        "-R",
+        "odoo:odoo",
+        &filestore(name),
+    ])
+}
+
+fn filestore(name: &str) -> String {
+    format!("{}/{}", FILESTORE, name)
+}
+
+fn strings(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+// Running the steps
+
+fn compose(project_dir: &Path, args: &[String]) -> Result<(), String> {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    docker::compose(project_dir, &args).map_err(running_hint)
+}
+
+fn compose_with_io(
+    project_dir: &Path,
+    args: &[String],
+    stdin: Stdio,
+    stdout: Stdio,
+) -> Result<(), String> {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    docker::compose_with_io(project_dir, &args, stdin, stdout).map_err(running_hint)
+}
+
+fn running_hint(error: String) -> String {
+    format!(
+        "{}: is the project running? Start it with `odoo-automation start`",
+        error
+    )
+}
+
+pub fn create(
+    project_dir: &Path,
+    name: &str,
+    lang: Option<&str>,
+    demo: bool,
+) -> Result<(), String> {
+    validate_name(name)?;
+    compose(project_dir, &create_args(name, lang, demo))
+}
+
+pub fn drop(project_dir: &Path, name: &str) -> Result<(), String> {
+    validate_name(name)?;
+    let project = Project::load(project_dir)?;
+    compose(project_dir, &drop_args(name, &project.postgres_version))?;
+    compose(project_dir, &remove_filestore_args(name))
+}
+
+// Zip with the SQL dump, the filestore and a manifest, like the backups of
+// Odoo's database manager
+pub fn backup(project_dir: &Path, name: &str, output: &Path) -> Result<(), String> {
+    validate_name(name)?;
+    let project = Project::load(project_dir)?;
+    let work = work_dir("backup")?;
+    let result = (|| {
+        let dump_path = work.join("dump.sql");
+        let dump = File::create(&dump_path)
+            .map_err(|e| format!("Failed to create {}: {}", dump_path.display(), e))?;
+        compose_with_io(
+            project_dir,
+            &dump_args(name),
+            Stdio::null(),
+            Stdio::from(dump),
+        )?;
+
+        let has_filestore = compose_with_io(
+            project_dir,
+            &has_filestore_args(name),
+            Stdio::null(),
+            Stdio::null(),
+        )
+        .is_ok();
+        if has_filestore {
+            compose(
+                project_dir,
+                &copy_filestore_out_args(name, &work.join("filestore")),
+            )?;
+        }
+
+        fs::write(
+            work.join("manifest.json"),
+            manifest(name, &project.odoo_version),
+        )
+        .map_err(|e| format!("Failed to write the backup manifest: {}", e))?;
+        zip_dir(&work, output).map_err(|e| format!("Failed to write {}: {}", output.display(), e))
+    })();
+    let _ = fs::remove_dir_all(&work);
+    result
+}
+
+// Load a backup made by `backup` or by Odoo's database manager into a new
+// database. The database is dropped again when loading it fails.
+pub fn restore(project_dir: &Path, name: &str, archive: &Path) -> Result<(), String> {
+    validate_name(name)?;
+    let project = Project::load(project_dir)?;
+    let work = work_dir("restore")?;
+    let result = (|| {
+        unzip(archive, &work)
+            .map_err(|e| format!("Failed to read {}: {}", archive.display(), e))?;
+        let dump_path = work.join("dump.sql");
+        let dump =
+            File::open(&dump_path).map_err(|_| format!("{} has no dump.sql", archive.display()))?;
+
+        compose(project_dir, &create_empty_args(name))?;
+        let loaded = compose_with_io(
+            project_dir,
+            &load_dump_args(name),
+            Stdio::from(dump),
+            Stdio::null(),
+        )
+        .and_then(|_| {
+            let filestore = work.join("filestore");
+            if !filestore.is_dir() {
+                return Ok(());
+            }
+            compose(project_dir, &prepare_filestore_args())?;
+            compose(project_dir, &copy_filestore_in_args(name, &filestore))?;
+            compose(project_dir, &own_filestore_args(name))
+        });
+        if loaded.is_err() {
+            let _ = compose(project_dir, &drop_args(name, &project.postgres_version));
+            let _ = compose(project_dir, &remove_filestore_args(name));
+        }
+        loaded
+    })();
+    let _ = fs::remove_dir_all(&work);
+    result
+}
+
+fn manifest(name: &str, odoo_version: &str) -> String {
+    format!(
+        "{{\n    \"odoo_dump\": \"1\",\n    \"db_name\": \"{}\",\n    \"version\": \"{}.0\",\n    \"major_version\": \"{}.0\"\n}}\n",
+        name, odoo_version, odoo_version
+    )
+}
+
+fn work_dir(purpose: &str) -> Result<PathBuf, String> {
+    let dir = std::env::temp_dir().join(format!(
+        "odoo-automation-{}-{}",
+        purpose,
+        std::process::id()
+    ));
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    Ok(dir)
+}
+
+fn zip_dir(dir: &Path, output: &Path) -> zip::result::ZipResult<()> {
+    let mut zip = ZipWriter::new(File::create(output)?);
+    add_to_zip(&mut zip, dir, dir)?;
+    zip.finish()?;
+    Ok(())
+}
+
+fn unzip(archive: &Path, dir: &Path) -> zip::result::ZipResult<()> {
+    ZipArchive::new(File::open(archive)?)?.extract(dir)
+}
+
+fn add_to_zip(zip: &mut ZipWriter<File>, root: &Path, dir: &Path) -> zip::result::ZipResult<()> {
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<_>>()?;
+    entries.sort();
+    for path in entries {
+        let name = path
+            .strip_prefix(root)
+            .unwrap()
+            .to_string_lossy()
+            .into_owned();
+        if path.is_dir() {
+            zip.add_directory(name, SimpleFileOptions::default())?;
+            add_to_zip(zip, root, &path)?;
+        } else {
+            // Dumps and filestores of production databases easily pass the
+            // 4 GiB of plain zip entries
+            let mut file = File::open(&path)?;
+            let large = file.metadata()?.len() > u32::MAX as u64;
+            zip.start_file(name, SimpleFileOptions::default().large_file(large))?;
+            io::copy(&mut file, zip)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validates_database_names() {
+        for name in ["prod", "shop-2024", "test_db", "v17.0"] {
+            assert_eq!(validate_name(name), Ok(()), "{}", name);
+        }
+        for name in ["", "-x", "a b", "a/b", "..", "x..y", "db;drop"] {
+            assert!(validate_name(name).is_err(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn create_initializes_base_in_the_web_container() {
+        assert_eq!(
+            create_args("shop", None, false).join(" "),
+            "exec -T web odoo -d shop -i base --stop-after-init --no-http --without-demo=all"
+        );
+        assert_eq!(
+            create_args("shop", Some("fr_FR"), true).join(" "),
+            "exec -T web odoo -d shop -i base --stop-after-init --no-http --load-language=fr_FR"
+        );
+    }
+
+    #[test]
+    fn drop_forces_only_on_recent_postgres() {
+        assert_eq!(
+            drop_args("shop", "16.4").join(" "),
+            "exec -T postgres dropdb -U odoo --force shop"
+        );
+        assert_eq!(
+            drop_args("shop", "12").join(" "),
+            "exec -T postgres dropdb -U odoo shop"
+        );
+        assert_eq!(
+            remove_filestore_args("shop").join(" "),
+            "exec -T web rm -rf /var/lib/odoo/filestore/shop"
+        );
+    }
+
+    #[test]
+    fn backup_and_restore_commands() {
+        assert_eq!(
+            dump_args("shop").join(" "),
+            "exec -T postgres pg_dump -U odoo --no-owner shop"
+        );
+        assert_eq!(
+            copy_filestore_out_args("shop", Path::new("/tmp/x/filestore")),
+            ["cp", "web:/var/lib/odoo/filestore/shop", "/tmp/x/filestore"]
+        );
+        assert_eq!(
+            load_dump_args("shop").join(" "),
+            "exec -T postgres psql -U odoo -d shop -q -v ON_ERROR_STOP=1"
+        );
+        assert_eq!(
+            own_filestore_args("shop").join(" "),
+            "exec -T -u root web chown -R odoo:odoo /var/lib/odoo/filestore/shop"
+        );
+    }
+
+    #[test]
+    fn zips_a_directory_tree() {
+        let dir = std::env::temp_dir().join(format!("odoo-automation-zip-{}", std::process::id()));
+        fs::create_dir_all(dir.join("in/filestore/ab")).unwrap();
+        fs::write(dir.join("in/dump.sql"), "SELECT 1;\n").unwrap();
+        fs::write(dir.join("in/filestore/ab/abcdef"), "attachment").unwrap();
+
+        zip_dir(&dir.join("in"), &dir.join("backup.zip")).unwrap();
+        unzip(&dir.join("backup.zip"), &dir.join("out")).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(dir.join("out/filestore/ab/abcdef")).unwrap(),
+            "attachment"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.join("out/dump.sql")).unwrap(),
+            "SELECT 1;\n"
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,43 @@
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+// Run `docker compose <args>` in the project directory
+pub fn compose(project_dir: &Path, args: &[&str]) -> Result<(), String> {
+    compose_with_io(project_dir, args, Stdio::inherit(), Stdio::inherit())
+}
+
+// The same, reading stdin from and writing stdout to the given streams
+pub fn compose_with_io(
+    project_dir: &Path,
+    args: &[&str],
+    stdin: Stdio,
+    stdout: Stdio,
+) -> Result<(), String> {
+    let status = Command::new("docker")
+        .arg("compose")
+        .args(args)
+        .current_dir(project_dir)
+        .stdin(stdin)
+        .stdout(stdout)
+        .status()
+        .map_err(|e| format!("Failed to execute docker compose: {}", e))?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("`docker compose {}` failed", args.join(" ")))
+    }
+}
+
+// Run `docker compose <args>` without any output, telling whether it succeeded
+pub fn compose_quiet(project_dir: &Path, args: &[&str]) -> bool {
+    Command::new("docker")
+        .arg("compose")
+        .args(args)
+        .current_dir(project_dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
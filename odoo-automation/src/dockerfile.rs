@@ -0,0 +1,243 @@
+use crate::compose::ComposeFile;
+use crate::manifest::Manifest;
+use crate::versions::Release;
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// Build context of the Odoo image, kept apart from the data folder which the
+// Docker daemon could not read
+pub const BUILD_DIR: &str = "docker";
+
+// Python modules whose pip package has another name
+const PIP_NAMES: [(&str, &str); 16] = [
+    ("Crypto", "pycryptodome"),
+    ("OpenSSL", "pyOpenSSL"),
+    ("PIL", "Pillow"),
+    ("bs4", "beautifulsoup4"),
+    ("cv2", "opencv-python-headless"),
+    ("dateutil", "python-dateutil"),
+    ("git", "GitPython"),
+    ("jwt", "PyJWT"),
+    ("ldap", "python-ldap"),
+    ("magic", "python-magic"),
+    ("serial", "pyserial"),
+    ("sklearn", "scikit-learn"),
+    ("slugify", "python-slugify"),
+    ("stdnum", "python-stdnum"),
+    ("usb", "pyusb"),
+    ("yaml", "PyYAML"),
+];
+
+// Programs whose Debian package has another name, or None when the official
+// image already ships them
+const APT_NAMES: [(&str, Option<&str>); 6] = [
+    ("convert", Some("imagemagick")),
+    ("gs", Some("ghostscript")),
+    ("lessc", None),
+    ("pdftotext", Some("poppler-utils")),
+    ("wkhtmltoimage", None),
+    ("wkhtmltopdf", None),
+];
+
+// What the addons need on top of the official image
+#[derive(Debug, Default, PartialEq)]
+pub struct Dependencies {
+    // pip requirements
+    pub python: BTreeSet<String>,
+    // Debian packages
+    pub system: BTreeSet<String>,
+}
+
+impl Dependencies {
+    // The external dependencies of the addon manifests, and the
+    // requirements.txt next to them
+    pub fn collect(addon_dirs: &[PathBuf]) -> Result<Dependencies, String> {
+        let mut dependencies = Dependencies::default();
+        for dir in addon_dirs {
+            let Ok(manifest) = Manifest::load(dir) else {
+                continue;
+            };
+            let external = &manifest.external_dependencies;
+            for module in &external.python {
+                dependencies.python.insert(pip_name(module));
+            }
+            for program in &external.bin {
+                dependencies.system.extend(apt_name(program));
+            }
+
+            let path = dir.join("requirements.txt");
+            match fs::read_to_string(&path) {
+                Ok(content) => dependencies.python.extend(requirements(&content)),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+            }
+        }
+        Ok(dependencies)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.python.is_empty() && self.system.is_empty()
+    }
+
+    // requirements.txt of the image, None when nothing is needed
+    pub fn requirements(&self) -> Option<String> {
+        if self.python.is_empty() {
+            return None;
+        }
+        let mut content = "# Generated by odoo-automation from the addons\n".to_string();
+        for requirement in &self.python {
+            content += &format!("{}\n", requirement);
+        }
+        Some(content)
+    }
+
+    // Dockerfile extending the official image with the dependencies
+    pub fn dockerfile(&self, release: &Release) -> String {
+        let mut lines = vec![
+            "# Generated by odoo-automation from the external dependencies of the addons"
+                .to_string(),
+            format!("FROM {}", release.image),
+            String::new(),
+            "USER root".to_string(),
+        ];
+        if !self.system.is_empty() {
+            lines.push(format!(
+                "RUN apt-get update \\\n    && apt-get install -y --no-install-recommends {} \\\n    && rm -rf /var/lib/apt/lists/*",
+                self.system.iter().cloned().collect::<Vec<_>>().join(" ")
+            ));
+        }
+        if !self.python.is_empty() {
+            let flag = if release.pip_break_system_packages {
+                " --break-system-packages"
+            } else {
+                ""
+            };
+            lines.push("COPY requirements.txt /tmp/requirements.txt".to_string());
+            lines.push(format!(
+                "RUN pip3 install --no-cache-dir{} -r /tmp/requirements.txt",
+                flag
+            ));
+        }
+        lines.push("USER odoo".to_string());
+        lines.join("\n") + "\n"
+    }
+}
+
+pub fn pip_name(module: &str) -> String {
+    PIP_NAMES
+        .iter()
+        .find(|(name, _)| *name == module)
+        .map(|(_, package)| package.to_string())
+        .unwrap_or_else(|| module.to_string())
+}
+
+pub fn apt_name(program: &str) -> Option<String> {
+    match APT_NAMES.iter().find(|(name, _)| *name == program) {
+        Some((_, package)) => package.map(str::to_string),
+        None => Some(program.to_string()),
+    }
+}
+
+// Requirements of a requirements.txt, without comments and pip options
+fn requirements(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .map(|line| line.split('#').next().unwrap().trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('-'))
+        .map(str::to_string)
+        .collect()
+}
+
+// The addon directories of the project
+pub fn addon_dirs(project_dir: &Path, addons: &[String]) -> Vec<PathBuf> {
+    addons
+        .iter()
+        .map(|addon| project_dir.join("custom_addons").join(addon))
+        .collect()
+}
+
+// Run Odoo from the image built in the project's build context instead of the
+// official one
+pub fn build_web(compose: &mut ComposeFile) {
+    let web = compose.services.get_mut("web").unwrap();
+    web.image = None;
+    web.build = Some(format!("./{}", BUILD_DIR));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::versions::Versions;
+
+    #[test]
+    fn collects_manifest_and_requirements_dependencies() {
+        let dir = std::env::temp_dir().join(format!("odoo-automation-deps-{}", std::process::id()));
+        let addon = |name: &str, manifest: &str| {
+            let path = dir.join(name);
+            fs::create_dir_all(&path).unwrap();
+            fs::write(path.join("__manifest__.py"), manifest).unwrap();
+            path
+        };
+        let ldap = addon(
+            "auth_ldap_extra",
+            "{'name': 'LDAP', 'external_dependencies': {'python': ['ldap'], 'bin': ['pdftotext', 'wkhtmltopdf']}}",
+        );
+        let export = addon("export_xlsx", "{'name': 'Export'}");
+        fs::write(
+            export.join("requirements.txt"),
+            "# Spreadsheets\nxlsxwriter>=3.0  # for exports\n-r other.txt\n\n",
+        )
+        .unwrap();
+
+        let dependencies = Dependencies::collect(&[ldap, export]).unwrap();
+        assert_eq!(
+            dependencies.python,
+            BTreeSet::from(["python-ldap".to_string(), "xlsxwriter>=3.0".to_string()])
+        );
+        assert_eq!(
+            dependencies.system,
+            BTreeSet::from(["poppler-utils".to_string()])
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn web_service_is_built() {
+        let mut compose = crate::compose::odoo_stack("odoo:17", "16", 8069, 8072, None);
+        build_web(&mut compose);
+        let yaml = compose.to_yaml().unwrap();
+        assert!(yaml.contains("build: \"./docker\""));
+        assert!(!yaml.contains("image: \"odoo:17\""));
+    }
+
+    #[test]
+    fn dockerfile_matches_the_odoo_release() {
+        let versions = Versions::parse(include_str!("../data/versions.yml")).unwrap();
+        let release = |version: &str| versions.find(version).unwrap();
+        let dependencies = Dependencies {
+            python: BTreeSet::from(["python-ldap".to_string()]),
+            system: BTreeSet::from(["poppler-utils".to_string()]),
+        };
+        let dockerfile = dependencies.dockerfile(release("18"));
+        assert!(dockerfile.starts_with("# Generated"));
+        assert!(dockerfile.contains("FROM odoo:18\n"));
+        assert!(dockerfile.contains("apt-get install -y --no-install-recommends poppler-utils"));
+        assert!(dockerfile.contains("pip3 install --no-cache-dir --break-system-packages -r"));
+        assert!(dockerfile.ends_with("USER odoo\n"));
+
+        // Ubuntu jammy's pip 22.0 has no --break-system-packages
+        let dockerfile = dependencies.dockerfile(release("17"));
+        assert!(dockerfile.contains("pip3 install --no-cache-dir -r"));
+        let dockerfile = dependencies.dockerfile(release("16"));
+        assert!(dockerfile.contains("pip3 install --no-cache-dir -r"));
+
+        let dependencies = Dependencies {
+            system: BTreeSet::from(["imagemagick".to_string()]),
+            ..Default::default()
+        };
+        assert!(dependencies.requirements().is_none());
+        assert!(!dependencies.dockerfile(release("17")).contains("pip3"));
+    }
+}
@@ -0,0 +1,371 @@
+use git2::build::{CheckoutBuilder, RepoBuilder};
+use git2::{
+    BranchType, Cred, CredentialType, Direction, FetchOptions, Remote, RemoteCallbacks, Repository,
+    StatusOptions,
+};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Everything that can go wrong while handling an addon repository
+#[derive(Debug)]
+pub enum GitError {
+    Clone { url: String, error: git2::Error },
+    Open { path: PathBuf, error: git2::Error },
+    Fetch { url: String, error: git2::Error },
+    BranchNotFound { branch: String, location: String },
+    DirtyWorkingTree { path: PathBuf },
+    Checkout { branch: String, error: git2::Error },
+    NotFastForward { branch: String },
+    Other(git2::Error),
+}
+
+impl fmt::Display for GitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GitError::Clone { url, error } => {
+                write!(f, "failed to clone {}: {}", url, error.message())
+            }
+            GitError::Open { path, error } => write!(
+                f,
+                "{} is not a usable git repository: {}",
+                path.display(),
+                error.message()
+            ),
+            GitError::Fetch { url, error } => {
+                write!(f, "failed to fetch {}: {}", url, error.message())
+            }
+            GitError::BranchNotFound { branch, location } => {
+                write!(f, "branch {} does not exist in {}", branch, location)
+            }
+            GitError::DirtyWorkingTree { path } => write!(
+                f,
+                "{} has uncommitted changes, commit or stash them first",
+                path.display()
+            ),
+            GitError::Checkout { branch, error } => {
+                write!(f, "failed to check out {}: {}", branch, error.message())
+            }
+            GitError::NotFastForward { branch } => write!(
+                f,
+                "{} has diverged from origin/{}, it can't be fast-forwarded",
+                branch, branch
+            ),
+            GitError::Other(error) => write!(f, "{}", error.message()),
+        }
+    }
+}
+
+impl std::error::Error for GitError {}
+
+impl From<git2::Error> for GitError {
+    fn from(error: git2::Error) -> GitError {
+        GitError::Other(error)
+    }
+}
+
+// Credentials from the ssh agent or the git credential helpers
+fn callbacks<'a>() -> RemoteCallbacks<'a> {
+    let mut attempts = 0;
+    let mut callbacks = RemoteCallbacks::new();
+    callbacks.credentials(move |url, username, allowed| {
+        // libgit2 keeps asking while the credentials are rejected
+        attempts += 1;
+        if attempts > 3 {
+            return Err(git2::Error::from_str("authentication failed"));
+        }
+        if allowed.contains(CredentialType::SSH_KEY) {
+            return Cred::ssh_key_from_agent(username.unwrap_or("git"));
+        }
+        if allowed.contains(CredentialType::USER_PASS_PLAINTEXT) {
+            let config = git2::Config::open_default()?;
+            return Cred::credential_helper(&config, url, username);
+        }
+        Cred::default()
+    });
+    callbacks
+}
+
+fn fetch_options<'a>() -> FetchOptions<'a> {
+    let mut options = FetchOptions::new();
+    options.remote_callbacks(callbacks());
+    options
+}
+
+// Branches published by a remote repository, without cloning it
+fn remote_branches(url: &str) -> Result<Vec<String>, GitError> {
+    let mut remote = Remote::create_detached(url)?;
+    let connection = remote
+        .connect_auth(Direction::Fetch, Some(callbacks()), None)
+        .map_err(|error| GitError::Fetch {
+            url: url.to_string(),
+            error,
+        })?;
+    let branches = connection
+        .list()?
+        .iter()
+        .filter_map(|head| head.name().strip_prefix("refs/heads/"))
+        .map(String::from)
+        .collect();
+    Ok(branches)
+}
+
+// Shallow clone of `url` into `path` with `branch` checked out: the lockfile
+// only needs the commit at HEAD
+pub fn clone(url: &str, path: &Path, branch: &str) -> Result<(), GitError> {
+    if !remote_branches(url)?.iter().any(|b| b == branch) {
+        return Err(GitError::BranchNotFound {
+            branch: branch.to_string(),
+            location: url.to_string(),
+        });
+    }
+
+    // libgit2 can't make shallow clones of a repository on the same machine
+    let mut options = fetch_options();
+    if !is_local(url) {
+        options.depth(1);
+    }
+    let result = RepoBuilder::new()
+        .branch(branch)
+        .fetch_options(options)
+        .clone(url, path);
+    if let Err(error) = result {
+        // Don't leave a half-cloned repository behind
+        let _ = fs::remove_dir_all(path);
+        return Err(GitError::Clone {
+            url: url.to_string(),
+            error,
+        });
+    }
+    Ok(())
+}
+
+fn is_local(url: &str) -> bool {
+    url.starts_with("file://") || Path::new(url).exists()
+}
+
+pub fn open(path: &Path) -> Result<Repository, GitError> {
+    Repository::open(path).map_err(|error| GitError::Open {
+        path: path.to_path_buf(),
+        error,
+    })
+}
+
+// Changes to tracked files; untracked files don't get in the way of a checkout
+fn is_dirty(repo: &Repository) -> Result<bool, GitError> {
+    let mut options = StatusOptions::new();
+    options.include_untracked(false).include_ignored(false);
+    Ok(!repo.statuses(Some(&mut options))?.is_empty())
+}
+
+pub fn current_branch(repo: &Repository) -> Option<String> {
+    let head = repo.head().ok()?;
+    if head.is_branch() {
+        head.shorthand().map(String::from)
+    } else {
+        None
+    }
+}
+
+// Update every remote-tracking branch from origin
+pub fn fetch(repo: &Repository) -> Result<(), GitError> {
+    let mut remote = repo.find_remote("origin")?;
+    let url = remote.url().unwrap_or("origin").to_string();
+    remote
+        .fetch(
+            &["+refs/heads/*:refs/remotes/origin/*"],
+            Some(&mut fetch_options()),
+            None,
+        )
+        .map_err(|error| GitError::Fetch { url, error })
+}
+
+// Switch the working tree to `branch`, creating it from origin if needed.
+// Refuses to switch when tracked files have uncommitted changes.
+pub fn checkout(repo: &Repository, branch: &str) -> Result<(), GitError> {
+    if current_branch(repo).as_deref() == Some(branch) {
+        return Ok(());
+    }
+    if is_dirty(repo)? {
+        return Err(GitError::DirtyWorkingTree {
+            path: workdir(repo),
+        });
+    }
+
+    let local = match repo.find_branch(branch, BranchType::Local) {
+        Ok(local) => local,
+        Err(_) => {
+            let upstream = format!("origin/{}", branch);
+            let remote = repo
+                .find_branch(&upstream, BranchType::Remote)
+                .map_err(|_| GitError::BranchNotFound {
+                    branch: branch.to_string(),
+                    location: workdir(repo).display().to_string(),
+                })?;
+            let commit = remote.get().peel_to_commit()?;
+            let mut local = repo.branch(branch, &commit, false)?;
+            local.set_upstream(Some(&upstream))?;
+            local
+        }
+    };
+
+    let refname = local.get().name().unwrap_or_default().to_string();
+    let checkout_error = |error| GitError::Checkout {
+        branch: branch.to_string(),
+        error,
+    };
+    let target = repo.revparse_single(&refname).map_err(checkout_error)?;
+    repo.checkout_tree(&target, Some(CheckoutBuilder::new().safe()))
+        .map_err(checkout_error)?;
+    repo.set_head(&refname).map_err(checkout_error)?;
+    Ok(())
+}
+
+pub fn has_branch(repo: &Repository, branch: &str) -> bool {
+    repo.find_branch(branch, BranchType::Local).is_ok()
+        || repo
+            .find_branch(&format!("origin/{}", branch), BranchType::Remote)
+            .is_ok()
+}
+
+// Fast-forward the checked out `branch` to origin, after a fetch.
+// Returns whether the branch moved.
+pub fn pull(repo: &Repository, branch: &str) -> Result<bool, GitError> {
+    if is_dirty(repo)? {
+        return Err(GitError::DirtyWorkingTree {
+            path: workdir(repo),
+        });
+    }
+
+    let upstream = repo
+        .find_branch(&format!("origin/{}", branch), BranchType::Remote)
+        .map_err(|_| GitError::BranchNotFound {
+            branch: branch.to_string(),
+            location: "origin".to_string(),
+        })?;
+    let upstream = repo.reference_to_annotated_commit(upstream.get())?;
+    let (analysis, _) = repo.merge_analysis(&[&upstream])?;
+
+    if analysis.is_up_to_date() {
+        Ok(false)
+    } else if analysis.is_fast_forward() {
+        let mut reference = repo.find_reference(&format!("refs/heads/{}", branch))?;
+        reference.set_target(upstream.id(), "odoo-automation: fast-forward")?;
+        // The tree was checked clean above, so forcing only applies the update
+        repo.checkout_head(Some(CheckoutBuilder::new().force()))?;
+        Ok(true)
+    } else {
+        Err(GitError::NotFastForward {
+            branch: branch.to_string(),
+        })
+    }
+}
+
+fn workdir(repo: &Repository) -> PathBuf {
+    repo.workdir().unwrap_or(repo.path()).to_path_buf()
+}
+
+// Commit checked out in `path`, when it is a git repository
+pub fn head_commit(path: &Path) -> Option<String> {
+    let repo = Repository::open(path).ok()?;
+    let commit = repo.head().ok()?.peel_to_commit().ok()?;
+    Some(commit.id().to_string())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use git2::{Oid, Signature};
+
+    // Bare repository standing for the remote of a source, with the 16.0 and
+    // 17.0 branches
+    pub fn origin(dir: &Path) -> Repository {
+        let _ = fs::remove_dir_all(dir);
+        let repo = Repository::init_bare(dir.join("origin.git")).unwrap();
+        commit(&repo, "16.0", "16.0 addons");
+        commit(&repo, "17.0", "17.0 addons");
+        repo
+    }
+
+    // Commit `content` as addons.txt on top of `branch`
+    pub fn commit(repo: &Repository, branch: &str, content: &str) -> Oid {
+        let refname = format!("refs/heads/{}", branch);
+        let parent = repo
+            .find_reference(&refname)
+            .and_then(|reference| reference.peel_to_commit())
+            .ok();
+        let blob = repo.blob(content.as_bytes()).unwrap();
+        let mut builder = repo.treebuilder(None).unwrap();
+        builder.insert("addons.txt", blob, 0o100644).unwrap();
+        let tree = repo.find_tree(builder.write().unwrap()).unwrap();
+        let signature = Signature::now("Test", "test@example.com").unwrap();
+        let parents: Vec<_> = parent.iter().collect();
+        repo.commit(
+            Some(&refname),
+            &signature,
+            &signature,
+            content,
+            &tree,
+            &parents,
+        )
+        .unwrap()
+    }
+
+    fn clone_17(dir: &Path) -> Repository {
+        let url = dir.join("origin.git").to_string_lossy().into_owned();
+        clone(&url, &dir.join("clone"), "17.0").unwrap();
+        open(&dir.join("clone")).unwrap()
+    }
+
+    #[test]
+    fn refuses_to_switch_a_dirty_checkout() {
+        let dir =
+            std::env::temp_dir().join(format!("odoo-automation-dirty-{}", std::process::id()));
+        origin(&dir);
+        let repo = clone_17(&dir);
+
+        fs::write(dir.join("clone/addons.txt"), "local change").unwrap();
+        assert!(matches!(
+            checkout(&repo, "16.0"),
+            Err(GitError::DirtyWorkingTree { .. })
+        ));
+        // Untracked files don't get in the way
+        fs::write(dir.join("clone/addons.txt"), "17.0 addons").unwrap();
+        fs::write(dir.join("clone/notes.txt"), "untracked").unwrap();
+        checkout(&repo, "16.0").unwrap();
+        assert_eq!(current_branch(&repo).as_deref(), Some("16.0"));
+        assert!(matches!(
+            checkout(&repo, "15.0"),
+            Err(GitError::BranchNotFound { .. })
+        ));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn pulls_only_fast_forwards() {
+        let dir = std::env::temp_dir().join(format!("odoo-automation-pull-{}", std::process::id()));
+        let origin = origin(&dir);
+        let repo = clone_17(&dir);
+
+        commit(&origin, "17.0", "new addons");
+        fetch(&repo).unwrap();
+        assert!(pull(&repo, "17.0").unwrap());
+        assert_eq!(
+            fs::read_to_string(dir.join("clone/addons.txt")).unwrap(),
+            "new addons"
+        );
+        assert!(!pull(&repo, "17.0").unwrap());
+
+        // Both sides moved on
+        commit(&origin, "17.0", "upstream addons");
+        commit(&repo, "17.0", "local addons");
+        repo.checkout_head(Some(CheckoutBuilder::new().force()))
+            .unwrap();
+        fetch(&repo).unwrap();
+        assert!(matches!(
+            pull(&repo, "17.0"),
+            Err(GitError::NotFastForward { .. })
+        ));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,229 @@
+use crate::docker;
+use crate::ports::HTTP_PORT;
+use crate::project::Project;
+use crate::proxy::Proxy;
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// How long `start` waits for the containers by default, and how often it checks
+pub const TIMEOUT: Duration = Duration::from_secs(180);
+const INTERVAL: Duration = Duration::from_secs(2);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Checks Odoo answers from inside the web container, where the proxy can't
+// get in the way
+const CONTAINER_PROBE: &str = "\
+import urllib.error, urllib.request
+try:
+    urllib.request.urlopen('http://localhost:{port}/web/health', timeout=5)
+except urllib.error.HTTPError as e:
+    if e.code != 404:
+        raise
+    urllib.request.urlopen('http://localhost:{port}/web/login', timeout=5)
+";
+
+// Status code of a GET request to http://host:port/path
+pub fn http_status(host: &str, port: u16, path: &str) -> io::Result<u16> {
+    let addr = (host, port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, host.to_string()))?;
+    let mut stream = TcpStream::connect_timeout(&addr, REQUEST_TIMEOUT)?;
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    stream.set_write_timeout(Some(REQUEST_TIMEOUT))?;
+    // In one write, as a server may close the connection after reading
+    // the first packet
+    let request = format!(
+        "GET {} HTTP/1.0\r\nHost: {}\r\nConnection: close\r\n\r\n",
+        path, host
+    );
+    stream.write_all(request.as_bytes())?;
+
+    // The status line is all that is needed
+    let mut head = Vec::new();
+    let mut buffer = [0; 256];
+    while !head.contains(&b'\n') {
+        let read = stream.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        head.extend_from_slice(&buffer[..read]);
+    }
+    let head = String::from_utf8_lossy(&head);
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "not an HTTP response");
+    let status = head.lines().next().ok_or_else(invalid)?;
+    match status.split_whitespace().collect::<Vec<_>>()[..] {
+        [version, code, ..] if version.starts_with("HTTP/") => code.parse().map_err(|_| invalid()),
+        _ => Err(invalid()),
+    }
+}
+
+// Recent Odoo versions answer /web/health; older ones only have the login
+// page, which redirects to the database manager when there is no database yet
+pub fn odoo_ready(host: &str, port: u16) -> bool {
+    match http_status(host, port, "/web/health") {
+        Ok(200) => true,
+        Ok(404) => matches!(http_status(host, port, "/web/login"), Ok(200..=399)),
+        _ => false,
+    }
+}
+
+// Call `ready` every `interval` until it returns true, for at most `timeout`
+pub fn wait_until(timeout: Duration, interval: Duration, mut ready: impl FnMut() -> bool) -> bool {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if ready() {
+            return true;
+        }
+        if Instant::now() + interval > deadline {
+            return false;
+        }
+        thread::sleep(interval);
+    }
+}
+
+// The last logs and the state of the containers, to see why the project
+// doesn't start
+pub fn show_logs(project_dir: &Path) {
+    let _ = docker::compose(project_dir, &["logs", "--tail", "50", "web", "postgres"]);
+    let _ = docker::compose(project_dir, &["ps", "--all"]);
+}
+
+// Wait for the database and then for Odoo after `docker compose up`. The
+// container logs are shown when either isn't ready in time.
+pub fn wait_for_project(
+    project_dir: &Path,
+    project: &Project,
+    timeout: Duration,
+) -> Result<(), String> {
+    let deadline = Instant::now() + timeout;
+    let remaining = || deadline.saturating_duration_since(Instant::now());
+    let failed = |what: &str| {
+        eprintln!(
+            "{} is not ready after {}s, the last logs:",
+            what,
+            timeout.as_secs()
+        );
+        show_logs(project_dir);
+        Err(format!("{} did not start", what))
+    };
+
+    println!("Waiting for PostgreSQL...");
+    let postgres_ready = wait_until(remaining(), INTERVAL, || {
+        docker::compose_quiet(
+            project_dir,
+            &[
+                "exec",
+                "-T",
+                "postgres",
+                "pg_isready",
+                "-U",
+                "odoo",
+                "-d",
+                "postgres",
+            ],
+        )
+    });
+    if !postgres_ready {
+        return failed("PostgreSQL");
+    }
+
+    println!("Waiting for Odoo...");
+    let probe = CONTAINER_PROBE.replace("{port}", &HTTP_PORT.to_string());
+    let odoo_ready = wait_until(remaining(), INTERVAL, || match project.proxy {
+        Proxy::None => odoo_ready("localhost", project.port),
+        _ => docker::compose_quiet(project_dir, &["exec", "-T", "web", "python3", "-c", &probe]),
+    });
+    if !odoo_ready {
+        return failed("Odoo");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    // An HTTP server answering `respond(path)` to each request, on a free port
+    fn stub_server(respond: impl Fn(&str) -> u16 + Send + 'static) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut request = [0; 1024];
+                let read = stream.read(&mut request).unwrap();
+                let request = String::from_utf8_lossy(&request[..read]);
+                let path = request.split_whitespace().nth(1).unwrap_or("/").to_string();
+                let status = respond(&path);
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 {} Stub\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    status
+                );
+            }
+        });
+        port
+    }
+
+    #[test]
+    fn health_endpoint_means_ready() {
+        let port = stub_server(|path| if path == "/web/health" { 200 } else { 500 });
+        assert_eq!(http_status("127.0.0.1", port, "/web/health").unwrap(), 200);
+        assert!(odoo_ready("127.0.0.1", port));
+    }
+
+    #[test]
+    fn older_versions_fall_back_to_the_login_page() {
+        let port = stub_server(|path| match path {
+            "/web/login" => 303,
+            _ => 404,
+        });
+        assert!(odoo_ready("127.0.0.1", port));
+
+        let port = stub_server(|path| match path {
+            "/web/login" => 502,
+            _ => 404,
+        });
+        assert!(!odoo_ready("127.0.0.1", port));
+    }
+
+    #[test]
+    fn waits_until_odoo_answers() {
+        // Like Odoo loading its registry: errors for the first requests
+        let requests = std::sync::atomic::AtomicU32::new(0);
+        let port = stub_server(move |_| {
+            if requests.fetch_add(1, std::sync::atomic::Ordering::SeqCst) < 3 {
+                503
+            } else {
+                200
+            }
+        });
+        let ready = wait_until(Duration::from_secs(5), Duration::from_millis(10), || {
+            odoo_ready("127.0.0.1", port)
+        });
+        assert!(ready);
+    }
+
+    #[test]
+    fn gives_up_after_the_timeout() {
+        // Nothing listens on a port that was just freed
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let start = Instant::now();
+        let ready = wait_until(
+            Duration::from_millis(200),
+            Duration::from_millis(50),
+            || odoo_ready("127.0.0.1", port),
+        );
+        assert!(!ready);
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+}
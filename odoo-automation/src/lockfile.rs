@@ -0,0 +1,188 @@
+use git2::{ObjectType, Oid};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+// Where the copied addons came from, kept in every project
+pub const LOCK_FILE: &str = "addons.lock";
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Lockfile {
+    #[serde(default)]
+    pub addons: Vec<LockedAddon>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LockedAddon {
+    pub name: String,
+    pub source: String,
+    // Git URL or local directory of the source
+    pub repo: String,
+    pub commit: Option<String>,
+    pub version: Option<String>,
+    // Checksum of the files as copied, to notice local changes
+    pub checksum: String,
+}
+
+impl Lockfile {
+    pub fn load(project_dir: &Path) -> Result<Lockfile, String> {
+        let path = project_dir.join(LOCK_FILE);
+        match fs::read_to_string(&path) {
+            Ok(content) => serde_yaml::from_str(&content)
+                .map_err(|e| format!("Invalid {}: {}", path.display(), e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Lockfile::default()),
+            Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+        }
+    }
+
+    pub fn to_yaml(&self) -> Result<String, String> {
+        serde_yaml::to_string(self).map_err(|e| format!("Failed to serialize {}: {}", LOCK_FILE, e))
+    }
+
+    pub fn save(&self, project_dir: &Path) -> Result<(), String> {
+        let path = project_dir.join(LOCK_FILE);
+        fs::write(&path, self.to_yaml()?)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+
+    // Add the addon, or replace its entry; entries are kept sorted by name
+    pub fn record(&mut self, addon: LockedAddon) {
+        self.remove(&addon.name);
+        self.addons.push(addon);
+        self.addons.sort_by(|a, b| a.name.cmp(&b.name));
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        self.addons.retain(|addon| addon.name != name);
+    }
+}
+
+// Git blob id of every file under `dir`, by relative path. Python bytecode
+// written by a running Odoo is not part of the addon.
+pub fn files(dir: &Path) -> io::Result<BTreeMap<String, Oid>> {
+    let mut files = BTreeMap::new();
+    collect_files(dir, dir, &mut files)?;
+    Ok(files)
+}
+
+fn collect_files(root: &Path, dir: &Path, files: &mut BTreeMap<String, Oid>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = path.file_name().unwrap().to_string_lossy();
+        if name == "__pycache__" || name.ends_with(".pyc") {
+            continue;
+        }
+        if path.is_dir() {
+            collect_files(root, &path, files)?;
+        } else {
+            let relative = path
+                .strip_prefix(root)
+                .unwrap()
+                .to_string_lossy()
+                .into_owned();
+            let oid =
+                Oid::hash_object(ObjectType::Blob, &fs::read(&path)?).map_err(io::Error::other)?;
+            files.insert(relative, oid);
+        }
+    }
+    Ok(())
+}
+
+// One checksum for the whole directory tree
+pub fn checksum(dir: &Path) -> io::Result<String> {
+    let listing: String = files(dir)?
+        .iter()
+        .map(|(path, oid)| format!("{} {}\n", oid, path))
+        .collect();
+    Oid::hash_object(ObjectType::Blob, listing.as_bytes())
+        .map(|oid| oid.to_string())
+        .map_err(io::Error::other)
+}
+
+// Files added (A), modified (M) and deleted (D) going from `old` to `new`
+pub fn diff(old: &Path, new: &Path) -> io::Result<Vec<String>> {
+    let old = files(old)?;
+    let new = files(new)?;
+    let mut changes = Vec::new();
+    for (path, oid) in &new {
+        match old.get(path) {
+            None => changes.push(format!("A {}", path)),
+            Some(old_oid) if old_oid != oid => changes.push(format!("M {}", path)),
+            Some(_) => {}
+        }
+    }
+    for path in old.keys().filter(|path| !new.contains_key(*path)) {
+        changes.push(format!("D {}", path));
+    }
+    changes.sort_by(|a, b| a[2..].cmp(&b[2..]));
+    Ok(changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tree(dir: &Path, files: &[(&str, &str)]) {
+        for (path, content) in files {
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+    }
+
+    #[test]
+    fn checksum_ignores_python_bytecode() {
+        let dir = std::env::temp_dir().join(format!("odoo-automation-lock-{}", std::process::id()));
+        tree(&dir, &[("__init__.py", "from . import models\n")]);
+        let before = checksum(&dir).unwrap();
+        tree(
+            &dir,
+            &[("__pycache__/__init__.cpython-312.pyc", "bytecode")],
+        );
+        assert_eq!(checksum(&dir).unwrap(), before);
+
+        tree(&dir, &[("__init__.py", "")]);
+        assert_ne!(checksum(&dir).unwrap(), before);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn diff_lists_changed_files() {
+        let dir = std::env::temp_dir().join(format!("odoo-automation-diff-{}", std::process::id()));
+        tree(
+            &dir.join("old"),
+            &[("a.py", "a"), ("b.py", "b"), ("views/c.xml", "c")],
+        );
+        tree(
+            &dir.join("new"),
+            &[("a.py", "a"), ("b.py", "B"), ("views/d.xml", "d")],
+        );
+        assert_eq!(
+            diff(&dir.join("old"), &dir.join("new")).unwrap(),
+            ["M b.py", "D views/c.xml", "A views/d.xml"]
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn record_replaces_entries() {
+        let addon = |name: &str, version: &str| LockedAddon {
+            name: name.to_string(),
+            source: "bbg".to_string(),
+            repo: "https://example.com/addons".to_string(),
+            commit: None,
+            version: Some(version.to_string()),
+            checksum: String::new(),
+        };
+        let mut lock = Lockfile::default();
+        lock.record(addon("sale_extra", "1.0"));
+        lock.record(addon("base_extra", "1.0"));
+        lock.record(addon("sale_extra", "1.1"));
+
+        let names: Vec<&str> = lock.addons.iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(names, ["base_extra", "sale_extra"]);
+        assert_eq!(lock.addons[1].version.as_deref(), Some("1.1"));
+    }
+}
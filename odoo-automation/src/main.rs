@@ -1,374 +1,1291 @@
-use dialoguer::{Input, MultiSelect, Select};
-use std::fs::read_dir;
-use std::fs::{self, File};
-use std::io::Write;
-use std::path::Path;
-use std::process::Command;
+mod addons;
+mod cli;
+mod compose;
+mod config;
+mod db;
+mod docker;
+mod dockerfile;
+mod git;
+mod health;
+mod lockfile;
+mod manifest;
+mod odoo_conf;
+mod plan;
+mod ports;
+mod project;
+mod proxy;
+mod registry;
+mod scaffold;
+mod secrets;
+mod services;
+mod sources;
+mod spec;
+mod template;
+mod versions;
+
+use addons::{AddonStatus, Pack};
+use clap::Parser;
+use cli::{AddonCommand, Cli, Commands, DbCommand, NewArgs};
+use config::Config;
+use dialoguer::{Confirm, Input, MultiSelect, Select};
+use dockerfile::Dependencies;
+use lockfile::Lockfile;
+use odoo_conf::{OdooConfig, Preset, Smtp};
+use plan::{Action, Plan};
+use project::{Edition, Project};
+use proxy::{Certificate, Proxy};
+use registry::Registry;
+use secrets::Secrets;
+use services::{ExtraService, ServiceTemplate, Templates};
+use spec::{normalize_version, ProjectSpec};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::Duration;
+use versions::Versions;
 
 fn main() {
-    // Ask for project name
-    let project_name: String = Input::new()
-        .with_prompt("Enter the project name")
-        .interact_text()
-        .unwrap();
+    let cli = Cli::parse();
 
-    // Ask for Odoo version
-    let odoo_versions = vec!["18", "17", "16", "15", "14", "13"];
-    let odoo_version_index = Select::new()
-        .with_prompt("Select Odoo version")
-        .items(&odoo_versions)
-        .interact()
-        .unwrap();
-    let odoo_version = odoo_versions[odoo_version_index];
+    match cli.command {
+        None => create_project(cli.new),
+        Some(Commands::New(args)) => create_project(args),
+        Some(Commands::AddAddon {
+            project,
+            addons,
+            pull_addons,
+        }) => add_addons(&project.path, addons, pull_addons),
+        Some(Commands::RemoveAddon { project, addons }) => remove_addons(&project.path, addons),
+        Some(Commands::Start { project, timeout }) => {
+            start_project(&project.path, Duration::from_secs(timeout))
+        }
+        Some(Commands::Stop { project }) => {
+            docker::compose(&project.path, &["stop"]).unwrap_or_else(|e| fail(&e));
+            println!("Docker compose has been stopped successfully!");
+        }
+        Some(Commands::Logs {
+            project,
+            service,
+            tail,
+        }) => {
+            let tail = tail.to_string();
+            let mut args = vec!["logs", "--follow", "--tail", &tail];
+            args.extend(service.as_deref());
+            docker::compose(&project.path, &args).unwrap_or_else(|e| fail(&e));
+        }
+        Some(Commands::Status { project }) => {
+            docker::compose(&project.path, &["ps"]).unwrap_or_else(|e| fail(&e));
+        }
+        Some(Commands::List) => list_projects(),
+        Some(Commands::Templates) => list_templates(),
+        Some(Commands::Forget { project }) => {
+            let mut registry = Registry::load().unwrap_or_else(|e| fail(&e));
+            match registry.forget(&project.path) {
+                Some(entry) => {
+                    registry.save().unwrap_or_else(|e| fail(&e));
+                    println!(
+                        "Forgot project '{}' ({})",
+                        entry.project.name,
+                        entry.path.display()
+                    );
+                }
+                None => fail(&format!(
+                    "{} is not a registered project",
+                    project.path.display()
+                )),
+            }
+        }
+        Some(Commands::Destroy { project, yes }) => destroy_project(&project.path, yes),
+        Some(Commands::RotateSecrets { project }) => {
+            secrets::rotate(&project.path).unwrap_or_else(|e| fail(&e));
+            println!("Secrets rotated! Run `docker compose up -d` to apply them to the running containers.");
+        }
+        Some(Commands::Addon { command }) => match command {
+            AddonCommand::New {
+                project,
+                name,
+                sample,
+            } => new_addon(&project.path, &name, sample),
+            AddonCommand::Outdated {
+                project,
+                pull_addons,
+            } => outdated_addons(&project.path, pull_addons),
+            AddonCommand::Update {
+                project,
+                addons,
+                pull_addons,
+                force,
+            } => update_addons(&project.path, addons, pull_addons, force),
+        },
+        Some(Commands::Db { command }) => manage_database(command),
+    }
+}
 
-    // Map Odoo version to PostgreSQL version
-    let postgres_version = match odoo_version {
-        "17" | "18" => "16",
-        _ => "13",
+fn create_project(args: NewArgs) {
+    // Answers from the template, then the spec file, overridden by the
+    // command-line flags
+    let spec = match &args.template {
+        Some(name) => template::load(name).unwrap_or_else(|e| fail(&e)),
+        None => ProjectSpec::default(),
     };
-
-    // Create folder structure
-    let folders = vec!["config", "addons", "custom_addons", "data"];
-    for folder in folders {
-        let path = format!("{}/{}", project_name, folder);
-        fs::create_dir_all(&path).expect("Failed to create directory");
+    let spec = match &args.spec {
+        Some(path) => spec.merge(ProjectSpec::from_file(path).unwrap_or_else(|e| fail(&e))),
+        None => spec,
+    };
+    let spec = spec.merge(args.answers());
+    if let Some(name) = &args.save_template {
+        template::validate_name(name).unwrap_or_else(|e| fail(&e));
+    }
+    for folder in spec.folders.iter().flatten() {
+        spec::validate_folder(folder).unwrap_or_else(|e| fail(&e));
     }
 
-    // Getting the port number
+    // Ask for project name
+    let project_name: String = match spec.name.clone() {
+        Some(name) => {
+            project::validate_name(&name).unwrap_or_else(|e| fail(&e));
+            name
+        }
+        None => Input::new()
+            .with_prompt("Enter the project name")
+            .validate_with(|name: &String| project::validate_name(name))
+            .interact_text()
+            .unwrap(),
+    };
 
-    let default_port = 8069;
+    // Re-running on an existing project keeps its answers and passwords, so
+    // only what was asked to change gets updated
+    let project_dir = PathBuf::from(&project_name);
+    let existing = if project_dir.exists() {
+        if !args.force {
+            fail(&format!(
+                "{} already exists, use --force to update it",
+                project_name
+            ));
+        }
+        if project_dir.join(project::PROJECT_FILE).exists() {
+            Some(Project::load(&project_dir).unwrap_or_else(|e| fail(&e)))
+        } else {
+            // Not a project of this tool: its files would be overwritten
+            let confirmed = Confirm::new()
+                .with_prompt(format!(
+                    "{} has no {}, write the project files into it anyway?",
+                    project_name,
+                    project::PROJECT_FILE
+                ))
+                .default(false)
+                .interact()
+                .unwrap_or(false);
+            if !confirmed {
+                println!("Nothing was changed.");
+                return;
+            }
+            None
+        }
+    } else {
+        None
+    };
+    let spec = match &existing {
+        Some(project) => ProjectSpec {
+            odoo_version: Some(project.odoo_version.clone()),
+            postgres_version: Some(project.postgres_version.clone()),
+            port: Some(project.port),
+            preset: Some(project.preset),
+            edition: Some(project.edition),
+            enterprise_path: project
+                .enterprise_path
+                .as_ref()
+                .map(|path| path.display().to_string()),
+            proxy: Some(project.proxy),
+            domain: project.domain.clone(),
+            services: Some(
+                project
+                    .services
+                    .iter()
+                    .map(|service| service.name.clone())
+                    .collect(),
+            ),
+            ..Default::default()
+        }
+        .merge(spec),
+        None => spec,
+    };
 
-    // Find an available port
-    let available_port = find_available_port(default_port);
-    println!("Using port: {}", available_port);
+    // Ask for Odoo version
+    let versions = Versions::load().unwrap_or_else(|e| fail(&e));
+    let release = match spec.odoo_version {
+        Some(version) => versions.find(&version).cloned().unwrap_or_else(|| {
+            fail(&format!(
+                "Unsupported Odoo version: {} (expected one of {})",
+                normalize_version(&version),
+                versions.names().join(", ")
+            ))
+        }),
+        None => {
+            let items: Vec<String> = versions.releases.iter().map(|r| r.describe()).collect();
+            let odoo_version_index = Select::new()
+                .with_prompt("Select Odoo version")
+                .items(&items)
+                .interact()
+                .unwrap();
+            versions.releases[odoo_version_index].clone()
+        }
+    };
+    let odoo_version = release.odoo.clone();
 
-    // Generate docker-compose.yml
-    let docker_compose_content = format!(
-        r#"version: '3'
-services:
-  web:
-    image: odoo:{}
-    ports:
-        - "{}:8069"
-    volumes:
-      - ./config:/etc/odoo
-      - ./addons:/mnt/extra-addons
-      - ./custom_addons:/mnt/custom-addons
-      - ./data:/var/lib/odoo
-    depends_on:
-      - postgres
-    environment:
-      - HOST=db
-      - USER=odoo
-      - PASSWORD=odoo
-    restart: always
-
-  postgres:
-    image: postgres:{}
-    environment:
-      - POSTGRES_DB=postgres
-      - POSTGRES_PASSWORD=odoo
-      - POSTGRES_USER=odoo
-    volumes:
-      - ./data/pgdata:/var/lib/postgresql/data
-    restart: always
-
-volumes:
-  odoo-web-data:
-  postgres-data:
-"#,
-        odoo_version, available_port, postgres_version
-    );
+    // PostgreSQL version supported by that Odoo version
+    let postgres_version = match spec.postgres_version {
+        Some(version) if release.postgres.allows(&version) => version,
+        Some(version) => fail(&format!(
+            "PostgreSQL {} is not supported by Odoo {} (expected {} to {})",
+            version, odoo_version, release.postgres.min, release.postgres.max
+        )),
+        None => release.postgres.default.clone(),
+    };
 
-    let docker_compose_path = format!("{}/docker-compose.yml", project_name);
-    let mut file = File::create(&docker_compose_path).expect("Failed to create file");
-    file.write_all(docker_compose_content.as_bytes())
-        .expect("Failed to write to file");
+    // Ask for the edition, and where the enterprise addons are
+    let edition = match spec.edition {
+        Some(Edition::Enterprise) if !release.enterprise => fail(&format!(
+            "There is no Enterprise edition of Odoo {}",
+            odoo_version
+        )),
+        Some(edition) => edition,
+        None if !release.enterprise => Edition::Community,
+        None => {
+            let names: Vec<&str> = Edition::ALL.iter().map(|edition| edition.name()).collect();
+            let index = Select::new()
+                .with_prompt("Select the Odoo edition")
+                .items(&names)
+                .default(0)
+                .interact()
+                .unwrap();
+            Edition::ALL[index]
+        }
+    };
+    let enterprise_path = match edition {
+        Edition::Community => None,
+        Edition::Enterprise => {
+            let path = match &spec.enterprise_path {
+                Some(path) => path.clone(),
+                None => Input::new()
+                    .with_prompt("Path to your checkout of the Odoo Enterprise addons")
+                    .interact_text()
+                    .unwrap(),
+            };
+            Some(enterprise_addons_path(&path).unwrap_or_else(|e| fail(&e)))
+        }
+    };
 
-    // Generate odoo.conf
-    let odoo_conf_content = format!(
-        r#"[options]
-addons_path = /mnt/extra-addons,/mnt/custom-addons
-data_dir = /var/lib/odoo
-admin_passwd = admin
-db_host = postgres
-db_port = 5432
-db_user = odoo
-db_password = odoo
-"#
+    // Use the requested port, or find an available one. Ports of the other
+    // registered projects are skipped even when they are stopped.
+    let mut registry = Registry::load().unwrap_or_else(|e| fail(&e));
+    registry.forget(&project_dir);
+    let mut claimed = registry.claimed_ports();
+    // A running project holds its own ports
+    let own_ports: Vec<u16> = existing
+        .iter()
+        .flat_map(|project| [project.port, project.longpolling_port])
+        .collect();
+    let available_port = match spec.port {
+        Some(port) if claimed.contains(&port) => {
+            fail(&format!("Port {} is claimed by another project", port))
+        }
+        Some(port) if ports::is_port_in_use(port) && !own_ports.contains(&port) => {
+            fail(&format!("Port {} is already in use", port))
+        }
+        Some(port) => port,
+        None => ports::find_available_port(ports::HTTP_PORT, &claimed).unwrap_or_else(|e| fail(&e)),
+    };
+    claimed.push(available_port);
+    let longpolling_port = match &existing {
+        Some(project) if project.port == available_port && project.longpolling_port != 0 => {
+            project.longpolling_port
+        }
+        _ => ports::find_available_port(
+            available_port.saturating_add(ports::LONGPOLLING_PORT - ports::HTTP_PORT),
+            &claimed,
+        )
+        .unwrap_or_else(|e| fail(&e)),
+    };
+    claimed.push(longpolling_port);
+    println!(
+        "Using port: {} (longpolling: {})",
+        available_port, longpolling_port
     );
 
-    let odoo_conf_path = format!("{}/config/odoo.conf", project_name);
-    let mut odoo_conf_file = File::create(&odoo_conf_path).expect("Failed to create odoo.conf");
-    odoo_conf_file
-        .write_all(odoo_conf_content.as_bytes())
-        .expect("Failed to write to odoo.conf");
-
-    // Change permissions
-    let permissions_commands = format!(
-        r#"chmod -R 777 {}/addons
-        chmod -R 777 {}/custom_addons
-        chmod -R 777 {}/data"#,
-        project_name, project_name, project_name
-    );
+    // Ask for a reverse proxy, and the domain it serves over HTTPS
+    let proxy = match spec.proxy {
+        Some(proxy) => proxy,
+        None => {
+            let names: Vec<&str> = Proxy::ALL.iter().map(|proxy| proxy.name()).collect();
+            let index = Select::new()
+                .with_prompt("Select a reverse proxy for HTTPS")
+                .items(&names)
+                .default(0)
+                .interact()
+                .unwrap();
+            Proxy::ALL[index]
+        }
+    };
+    let (domain, https_port) = if proxy == Proxy::None {
+        (None, None)
+    } else {
+        let domain = spec.domain.clone().unwrap_or_else(|| {
+            Input::new()
+                .with_prompt("Domain name")
+                .default(proxy::default_domain(&project_name))
+                .validate_with(|domain: &String| proxy::validate_domain(domain))
+                .interact_text()
+                .unwrap()
+        });
+        proxy::validate_domain(&domain).unwrap_or_else(|e| fail(&e));
+        let https_port = match existing.as_ref().and_then(|project| project.https_port) {
+            Some(port) => port,
+            None => {
+                ports::find_available_port(ports::HTTPS_PORT, &claimed).unwrap_or_else(|e| fail(&e))
+            }
+        };
+        claimed.push(https_port);
+        println!("Serving https://{} on port {}", domain, https_port);
+        (Some(domain), Some(https_port))
+    };
 
-    let _output = Command::new("sh")
-        .arg("-c")
-        .arg(permissions_commands)
-        .status()
-        .expect("Failed to execute command");
+    // Ask for companion services, each published on the next free ports
+    let templates = Templates::load().unwrap_or_else(|e| fail(&e));
+    let chosen: Vec<&ServiceTemplate> = match &spec.services {
+        Some(names) => names
+            .iter()
+            .map(|name| {
+                templates.find(name).unwrap_or_else(|| {
+                    fail(&format!(
+                        "Unknown service '{}' (expected one of {})",
+                        name,
+                        templates.names().join(", ")
+                    ))
+                })
+            })
+            .collect(),
+        None => {
+            let items: Vec<&str> = templates
+                .services
+                .iter()
+                .map(|template| template.description.as_str())
+                .collect();
+            MultiSelect::new()
+                .with_prompt("Select extra services to run with Odoo")
+                .items(&items)
+                .interact()
+                .unwrap()
+                .into_iter()
+                .map(|i| &templates.services[i])
+                .collect()
+        }
+    };
+    let mut extra_services = Vec::new();
+    for template in &chosen {
+        let previous = existing.as_ref().and_then(|project| {
+            project
+                .services
+                .iter()
+                .find(|service| service.name == template.name)
+        });
+        let ports: Vec<u16> = match previous {
+            Some(service) if service.ports.len() == template.ports.len() => service.ports.clone(),
+            _ => template
+                .ports
+                .iter()
+                .map(|port| {
+                    let host = ports::find_available_port(port.host, &claimed)
+                        .unwrap_or_else(|e| fail(&e));
+                    claimed.push(host);
+                    host
+                })
+                .collect(),
+        };
+        for (port, host) in template.ports.iter().zip(&ports) {
+            println!(
+                "Using port {} for the {} of {}",
+                host, port.label, template.name
+            );
+        }
+        if let Some(note) = &template.note {
+            println!("Note: {}", note);
+        }
+        extra_services.push(ExtraService {
+            name: template.name.clone(),
+            ports,
+        });
+    }
 
-    println!("Permissions have been set successfully!");
+    // Ask for the server settings preset
+    let preset = match spec.preset {
+        Some(preset) => preset,
+        None => {
+            let names: Vec<&str> = Preset::ALL.iter().map(|preset| preset.name()).collect();
+            let index = Select::new()
+                .with_prompt("Select the server configuration preset")
+                .items(&names)
+                .default(0)
+                .interact()
+                .unwrap();
+            Preset::ALL[index]
+        }
+    };
 
     // check if you want to add addons from the repository
-    let add_addons: bool = Select::new()
-        .with_prompt("Do you want to add addons from the repository?")
-        .default(0)
-        .items(&["Yes", "No"])
-        .interact()
-        .unwrap()
-        == 0;
+    let add_addons: bool = match &spec.addons {
+        Some(addons) => !addons.is_empty(),
+        None => ask_yes_no("Do you want to add addons from the repository?"),
+    };
 
-    // if no, exit
+    let mut pack = None;
+    let mut selected = Vec::new();
+    let mut resolved = Vec::new();
+    let mut unsynced = Vec::new();
+    let config = Config::load().unwrap_or_else(|e| fail(&e));
     if add_addons {
-        // Clone the repository if it doesn't exist
-        let repo_path = format!(
-            "{}/Documents/BBG-ODOO-ADDONS-PACK",
-            std::env::var("HOME").unwrap()
+        match Pack::load(
+            &config.sources,
+            &odoo_version,
+            spec.pull_addons,
+            args.dry_run,
+        ) {
+            Err(e) => eprintln!("Error: {}\nSkipping the addons.", e),
+            Ok(loaded) if loaded.entries.is_empty() => {
+                println!("No addons found in the addon sources!");
+                unsynced = loaded.unsynced;
+            }
+            Ok(loaded) => {
+                unsynced = loaded.unsynced.clone();
+                selected =
+                    addons::choose_addons(&loaded, spec.addons.as_deref()).unwrap_or_else(|e| {
+                        if unsynced.is_empty() {
+                            fail(&e)
+                        }
+                        fail(&format!(
+                            "{} (the dry run didn't sync the sources: {})",
+                            e,
+                            unsynced.join(", ")
+                        ))
+                    });
+                let installed = addons::installed_addons(&project_dir);
+                resolved = addons::resolve_addons(&loaded, &selected, &odoo_version, &installed)
+                    .unwrap_or_else(|e| fail(&e));
+                pack = Some(loaded);
+            }
+        }
+    }
+
+    // Python and system packages the addons need, installed in an image built
+    // for the project
+    let mut addon_dirs =
+        dockerfile::addon_dirs(&project_dir, &addons::installed_addons(&project_dir));
+    if let Some(pack) = &pack {
+        addon_dirs.extend(resolved.iter().map(|name| pack.addons[name].path.clone()));
+    }
+    let dependencies = Dependencies::collect(&addon_dirs).unwrap_or_else(|e| fail(&e));
+    if !dependencies.is_empty() {
+        println!(
+            "The Odoo image is extended with: {}",
+            dependencies
+                .python
+                .iter()
+                .chain(&dependencies.system)
+                .cloned()
+                .collect::<Vec<_>>()
+                .join(", ")
         );
-        if Path::new(&repo_path).exists() {
-            println!("Addons already exists!");
-            //  checkout the correct branch and pull the latest changes
-            let status = Command::new("git")
-                .arg("checkout")
-                .arg(format!("{}.0", odoo_version))
-                .current_dir(&repo_path)
-                .status()
-                .expect("Failed to execute git checkout");
-
-            if status.success() {
-                println!("Switched to branch: {}.0", odoo_version);
-            } else {
-                eprintln!("Failed to switch to branch: {}.0", odoo_version);
+    }
+
+    // check if you want to start docker compose
+    let start_docker: bool = spec
+        .start_docker
+        .unwrap_or_else(|| ask_yes_no("Do you want to start the docker-compose?"));
+
+    // Every answer is known: plan the changes before making any of them
+    let mut plan = Plan::default();
+
+    // Create folder structure: the folders mounted in the containers, then
+    // the ones of the template
+    let folders = vec!["config", "addons", "custom_addons", "data"];
+    for folder in &folders {
+        plan.push(Action::CreateDir(project_dir.join(folder)));
+    }
+    for folder in spec.folders.iter().flatten() {
+        plan.push(Action::CreateDir(project_dir.join(folder)));
+    }
+
+    // Generate the project passwords, read by docker compose from .env
+    let secrets = Secrets::read_env_file(&project_dir).unwrap_or_else(Secrets::generate);
+    plan.push(Action::WriteFile {
+        path: project_dir.join(".env"),
+        content: secrets.env_file(),
+        mode: Some(0o600),
+    });
+
+    // Settings of odoo.conf, which the proxy routes depend on
+    let mut odoo_config = OdooConfig::preset(preset, &secrets.admin_password, &secrets.db_password);
+    odoo_config.smtp = spec.smtp.clone();
+    if enterprise_path.is_some() {
+        // Enterprise modules override community ones, so they come first
+        odoo_config
+            .addons_path
+            .insert(0, compose::ENTERPRISE_ADDONS.to_string());
+    }
+    // Mails go to the mail catcher, when there is one
+    for template in &chosen {
+        if let Some(port) = template.smtp_port {
+            if spec.smtp.is_some() {
+                eprintln!(
+                    "Warning: Odoo sends its mails to {} instead of the SMTP server of the spec",
+                    template.name
+                );
             }
+            odoo_config.smtp = Some(Smtp {
+                server: template.name.clone(),
+                port,
+                user: None,
+                password: None,
+                ssl: false,
+                email_from: None,
+            });
+        }
+    }
+    // Behind the proxy, Odoo must trust its X-Forwarded-* headers
+    odoo_config.proxy_mode |= proxy != Proxy::None;
 
-            // chek if want to pull the latest changes
-            let pull_latest: bool = Select::new()
-                .with_prompt("Do you want to pull the latest changes?")
-                .default(0)
-                .items(&["Yes", "No"])
-                .interact()
-                .unwrap()
-                == 0;
-            if pull_latest {
-                let status = Command::new("git")
-                    .arg("pull")
-                    .current_dir(&repo_path)
-                    .status()
-                    .expect("Failed to execute git pull");
-
-                if status.success() {
-                    println!("Repository updated successfully!");
-                } else {
-                    eprintln!("Failed to update repository!");
-                }
+    // Generate docker-compose.yml
+    let mut stack = compose::odoo_stack(
+        &release.image,
+        &postgres_version,
+        available_port,
+        longpolling_port,
+        enterprise_path.as_deref(),
+    );
+    if let (Some(domain), Some(https_port)) = (&domain, https_port) {
+        proxy::add_to(
+            &mut stack,
+            proxy,
+            &project_name,
+            domain,
+            available_port,
+            https_port,
+            &odoo_version,
+            &odoo_config,
+        );
+    }
+    for (template, service) in chosen.iter().zip(&extra_services) {
+        services::add_to(&mut stack, template, &project_name, &service.ports);
+    }
+    if !dependencies.is_empty() {
+        dockerfile::build_web(&mut stack);
+    }
+    let docker_compose_content = stack
+        .to_yaml()
+        .unwrap_or_else(|e| fail(&format!("Failed to serialize docker-compose.yml: {}", e)));
+    plan.write_file(
+        project_dir.join("docker-compose.yml"),
+        docker_compose_content,
+    );
+
+    // Generate odoo.conf
+    plan.write_file(
+        project_dir.join("config/odoo.conf"),
+        odoo_config.render(&odoo_version),
+    );
+
+    // Proxy configuration, with a self-signed certificate for the domain
+    if let (Some(domain), Some(https_port)) = (&domain, https_port) {
+        let same_domain = existing
+            .as_ref()
+            .is_some_and(|project| project.domain.as_ref() == Some(domain));
+        let certificate = match Certificate::read(&project_dir) {
+            Some(certificate) if same_domain => certificate,
+            _ => Certificate::self_signed(domain).unwrap_or_else(|e| fail(&e)),
+        };
+        plan.push(Action::CreateDir(project_dir.join("proxy/certs")));
+        plan.write_file(project_dir.join(proxy::CERT_FILE), certificate.cert);
+        plan.push(Action::WriteFile {
+            path: project_dir.join(proxy::KEY_FILE),
+            content: certificate.key,
+            mode: Some(0o600),
+        });
+        match proxy {
+            Proxy::Nginx => plan.write_file(
+                project_dir.join(proxy::NGINX_FILE),
+                proxy::nginx_conf(domain, https_port, &odoo_version, &odoo_config),
+            ),
+            Proxy::Traefik => {
+                plan.write_file(project_dir.join(proxy::TRAEFIK_FILE), proxy::traefik_conf())
+            }
+            Proxy::None => {}
+        }
+    }
+
+    // Dockerfile of the Odoo image with the addon dependencies
+    if !dependencies.is_empty() {
+        let build_dir = project_dir.join(dockerfile::BUILD_DIR);
+        plan.push(Action::CreateDir(build_dir.clone()));
+        plan.write_file(
+            build_dir.join("Dockerfile"),
+            dependencies.dockerfile(&release),
+        );
+        if let Some(requirements) = dependencies.requirements() {
+            plan.write_file(build_dir.join("requirements.txt"), requirements);
+        }
+    }
+
+    // Configuration files of the companion services
+    for template in &chosen {
+        for (path, content) in template.files(&project_name) {
+            let path = project_dir.join(path);
+            let dir = Action::CreateDir(path.parent().unwrap().to_path_buf());
+            if !plan.actions.contains(&dir) {
+                plan.push(dir);
             }
+            plan.write_file(path, content);
+        }
+    }
+
+    // Copy the chosen addons, recording where they come from
+    if let Some(pack) = &pack {
+        for action in addons::copy_actions(pack, &resolved, &project_dir) {
+            plan.push(action);
+        }
+        let mut lock = Lockfile::load(&project_dir).unwrap_or_else(|e| fail(&e));
+        for addon in &resolved {
+            lock.record(addons::lock_entry(pack, addon).unwrap_or_else(|e| fail(&e)));
+        }
+        plan.write_file(
+            project_dir.join(lockfile::LOCK_FILE),
+            lock.to_yaml().unwrap_or_else(|e| fail(&e)),
+        );
+    }
+
+    // Let the Odoo container write to the mounted folders
+    for folder in &folders[1..] {
+        let path = project_dir.join(folder);
+        plan.push(if spec.world_writable == Some(true) {
+            Action::MakeWorldWritable(path)
         } else {
-            println!("Cloning Bigbang Odoo Addons Pack repository...");
-
-            // Clone the repository
-            let status = Command::new("git")
-                .arg("clone")
-                .arg("https://github.com/bbgstack/BBG-ODOO-ADDONS-PACK")
-                .arg(&repo_path)
-                .arg("--depth")
-                .arg("1")
-                .arg("--branch")
-                .arg(format!("{}.0", odoo_version))
-                .status()
-                .expect("Failed to execute git clone");
-
-            if status.success() {
-                println!("Repository cloned successfully!");
-            } else {
-                eprintln!("Failed to clone repository!");
+            Action::SetOwner {
+                path,
+                uid: config.odoo_uid,
+                gid: config.odoo_gid,
             }
+        });
+    }
+
+    let mut project_addons = existing
+        .as_ref()
+        .map(|project| project.addons.clone())
+        .unwrap_or_default();
+    project_addons.extend(resolved.iter().cloned());
+    let project = Project {
+        name: project_name.clone(),
+        odoo_version: odoo_version.clone(),
+        postgres_version: postgres_version.clone(),
+        port: available_port,
+        longpolling_port,
+        preset,
+        edition,
+        enterprise_path: enterprise_path.clone(),
+        proxy,
+        domain,
+        https_port,
+        services: extra_services,
+        addons: project_addons,
+    };
+    plan.write_file(
+        project_dir.join(project::PROJECT_FILE),
+        project.to_yaml().unwrap_or_else(|e| fail(&e)),
+    );
+    let url = project.url();
+    let mut service_urls = Vec::new();
+    for (template, service) in chosen.iter().zip(&project.services) {
+        for (port, host) in template.ports.iter().zip(&service.ports) {
+            service_urls.push(format!(
+                "{} {}: http://localhost:{}",
+                template.name, port.label, host
+            ));
         }
+    }
+    plan.push(Action::Register {
+        dir: project_dir.clone(),
+        project,
+    });
 
-        // list all the addons
-        // make an array of all the selected addons
+    // Starting the containers is not part of the project creation: a failure
+    // there leaves the project in place
+    let mut start = Plan::default();
+    if start_docker {
+        // Rebuild the Odoo image in case the addon dependencies changed
+        let args: &[&str] = if dependencies.is_empty() {
+            &["compose", "up", "-d"]
+        } else {
+            &["compose", "up", "-d", "--build"]
+        };
+        start.run("docker", args, Some(project_dir.clone()));
+    }
 
-        // List all addons in the repository
-        let addons: Vec<String> = read_dir(&repo_path)
-            .expect("Failed to read repository directory")
-            .filter_map(|entry| {
-                let entry = entry.expect("Failed to read entry");
-                let path = entry.path();
-                if path.is_dir() {
-                    Some(path.file_name().unwrap().to_string_lossy().into_owned())
-                } else {
-                    None
-                }
-            })
-            .collect();
+    if project_dir.exists() {
+        plan.drop_unchanged();
+    }
 
-        if addons.is_empty() {
-            println!("No addons found in the repository!");
+    if args.dry_run {
+        println!(
+            "\nPlanned changes:\n{}{}",
+            plan.describe(),
+            start.describe()
+        );
+        // Applying would copy addons from sources a real run syncs first
+        if !unsynced.is_empty() {
+            println!(
+                "The addon sources are not synced for Odoo {}: {}.\nRun without --dry-run to sync them. Nothing was changed.",
+                odoo_version,
+                unsynced.join(", ")
+            );
             return;
         }
-
-        // Multi-select prompt for addons
-        let selected_addons: Vec<usize> = MultiSelect::new()
-            .with_prompt("Select addons to include in your project")
-            .items(&addons)
+        if add_addons {
+            println!("The addon sources were read as they are on disk, without pulling them.");
+        }
+        let confirmed = Confirm::new()
+            .with_prompt("Apply these changes?")
+            .default(false)
             .interact()
-            .expect("Failed to select addons");
-
-        // Copy selected addons to custom_addons folder
-        for index in selected_addons {
-            let addon_name = &addons[index];
-            let src = format!("{}/{}", repo_path, addon_name);
-            let dest = format!("{}/custom_addons", project_name); // Destination is always custom_addons
+            .unwrap_or(false);
+        if !confirmed {
+            println!("Nothing was changed.");
+            return;
+        }
+    }
 
-            // Copy directory or group
-            copy_dir_all(&src, &dest).expect("Failed to copy addon");
-            println!("Copied addon: {}", addon_name);
+    if project_dir.exists() {
+        if !args.dry_run {
+            print!("Updating {}:\n{}", project_name, plan.describe());
         }
+        plan.execute()
+            .unwrap_or_else(|e| fail(&format!("{}, the changes were undone", e)));
+    } else {
+        plan.execute_into(&project_dir)
+            .unwrap_or_else(|e| fail(&format!("{}, the project was not created", e)));
+    }
+    if let Some(pack) = &pack {
+        addons::report_copied(pack, &resolved);
     }
 
     println!(
-        "Project '{}' with Odoo {}  has been created successfully!",
-        project_name, odoo_version
+        "Project '{}' with Odoo {}  has been {} successfully!",
+        project_name,
+        odoo_version,
+        if existing.is_some() {
+            "updated"
+        } else {
+            "created"
+        }
     );
+    for service_url in &service_urls {
+        println!("  {}", service_url);
+    }
+    if let Some(name) = &args.save_template {
+        let answers = ProjectSpec {
+            odoo_version: Some(odoo_version.clone()),
+            postgres_version: Some(postgres_version),
+            preset: Some(preset),
+            edition: Some(edition),
+            enterprise_path: enterprise_path.map(|path| path.display().to_string()),
+            proxy: Some(proxy),
+            smtp: spec.smtp.clone(),
+            addons: Some(selected),
+            services: Some(chosen.iter().map(|t| t.name.clone()).collect()),
+            folders: spec.folders.clone(),
+            pull_addons: spec.pull_addons,
+            start_docker: Some(start_docker),
+            world_writable: spec.world_writable,
+            ..Default::default()
+        };
+        match template::save(name, answers) {
+            Ok(path) => println!(
+                "Saved the answers as template '{}' in {}",
+                name,
+                path.display()
+            ),
+            Err(e) => eprintln!("Error: {}", e),
+        }
+    }
+    if start_docker {
+        let started = start
+            .execute()
+            .map_err(|e| {
+                eprintln!("The containers failed to start, the last logs:");
+                health::show_logs(&project_dir);
+                e.to_string()
+            })
+            .and_then(|_| {
+                let project = Project::load(&project_dir)?;
+                health::wait_for_project(&project_dir, &project, health::TIMEOUT)
+            });
+        match started {
+            Ok(()) => println!("Odoo is ready, open {}", url),
+            Err(e) => fail(&format!(
+                "{}. Fix the problem and run `odoo-automation start --project {}`",
+                e, project_name
+            )),
+        }
+    }
+}
 
-    // check if you want to start docker compose
-    let start_docker: bool = Select::new()
-        .with_prompt("Do you want to start the docker-compose?")
-        .default(0)
-        .items(&["Yes", "No"])
-        .interact()
-        .unwrap()
-        == 0;
+// Start the containers of a project and wait until Odoo answers
+fn start_project(project_dir: &Path, timeout: Duration) {
+    let project = Project::load(project_dir).unwrap_or_else(|e| fail(&e));
+    if let Err(e) = docker::compose(project_dir, &["up", "-d"]) {
+        eprintln!("The containers failed to start, the last logs:");
+        health::show_logs(project_dir);
+        fail(&e);
+    }
+    health::wait_for_project(project_dir, &project, timeout).unwrap_or_else(|e| fail(&e));
+    println!("Odoo is ready, open {}", project.url());
+}
 
-    if start_docker {
-        let _output = Command::new("docker")
-            .arg("compose")
-            .arg("up")
-            .arg("-d")
-            .current_dir(&project_name)
-            .status()
-            .expect("Failed to execute command");
+// Absolute path of an enterprise addons checkout, checked to look like one
+fn enterprise_addons_path(path: &str) -> Result<PathBuf, String> {
+    let path = config::expand_home(path);
+    let path = fs::canonicalize(&path)
+        .map_err(|e| format!("Enterprise addons {}: {}", path.display(), e))?;
+    if !path.is_dir() {
+        return Err(format!(
+            "Enterprise addons {} is not a directory",
+            path.display()
+        ));
+    }
+    if !path.join("web_enterprise").is_dir() {
+        eprintln!(
+            "Warning: {} has no web_enterprise module, is it the enterprise addons checkout?",
+            path.display()
+        );
+    }
+    Ok(path)
+}
+
+// Copy addons from the pack into an existing project
+fn add_addons(project_dir: &Path, wanted: Vec<String>, pull: Option<bool>) {
+    let mut project = Project::load(project_dir).unwrap_or_else(|e| fail(&e));
+
+    let config = Config::load().unwrap_or_else(|e| fail(&e));
+    let pack = Pack::load(&config.sources, &project.odoo_version, pull, false)
+        .unwrap_or_else(|e| fail(&e));
+    if pack.entries.is_empty() {
+        fail("No addons found in the addon sources!");
+    }
+    let wanted = if wanted.is_empty() {
+        None
+    } else {
+        Some(wanted)
+    };
+    let selected = addons::choose_addons(&pack, wanted.as_deref()).unwrap_or_else(|e| fail(&e));
 
-        println!("Docker compose has been started successfully!");
+    let installed = addons::installed_addons(project_dir);
+    let resolved = addons::resolve_addons(&pack, &selected, &project.odoo_version, &installed)
+        .unwrap_or_else(|e| fail(&e));
+    if resolved.is_empty() {
+        println!("Nothing to add, the selected addons are already in the project.");
+    }
+    addons::copy_addons(&pack, &resolved, project_dir).unwrap_or_else(|e| fail(&e));
+    warn_if_image_outdated(project_dir, &project);
+    for addon in resolved {
+        if !project.addons.contains(&addon) {
+            project.addons.push(addon);
+        }
+    }
+    project.save(project_dir).unwrap_or_else(|e| fail(&e));
+    registry::record(project_dir, &project).unwrap_or_else(|e| fail(&e));
+}
+
+// The Dockerfile is generated by the wizard, which has to be run again when
+// the addons need other packages
+fn warn_if_image_outdated(project_dir: &Path, project: &Project) {
+    let installed = addons::installed_addons(project_dir);
+    let Ok(dependencies) = Dependencies::collect(&dockerfile::addon_dirs(project_dir, &installed))
+    else {
+        return;
+    };
+    let Ok(versions) = Versions::load() else {
+        return;
+    };
+    let Some(release) = versions.find(&project.odoo_version) else {
+        return;
+    };
+    let path = project_dir.join(dockerfile::BUILD_DIR).join("Dockerfile");
+    let current = fs::read_to_string(&path).ok();
+    let wanted = (!dependencies.is_empty()).then(|| dependencies.dockerfile(release));
+    if current != wanted {
+        eprintln!(
+            "Warning: the addons need other packages than the Odoo image has, run `odoo-automation new --name {} --force` from {} to update it",
+            project.name,
+            project_dir
+                .canonicalize()
+                .ok()
+                .and_then(|dir| dir.parent().map(Path::to_path_buf))
+                .unwrap_or_default()
+                .display()
+        );
     }
 }
 
-// Function to check if a port is in use
-fn is_port_in_use(port: u16) -> bool {
-    // Run the `ss` command to check if the port is in use
-    let output = Command::new("ss")
-        .arg("-tuln")
-        .arg(format!("sport {}", port))
-        .output();
+// Delete addons from an existing project's custom_addons folder
+fn remove_addons(project_dir: &Path, wanted: Vec<String>) {
+    let mut project = Project::load(project_dir).unwrap_or_else(|e| fail(&e));
 
-    match output {
-        Ok(output) => {
-            // Convert the output to a string
-            let output_str = String::from_utf8_lossy(&output.stdout);
+    let installed = addons::installed_addons(project_dir);
+    let selected: Vec<String> = if wanted.is_empty() {
+        MultiSelect::new()
+            .with_prompt("Select addons to remove from your project")
+            .items(&installed)
+            .interact()
+            .expect("Failed to select addons")
+            .into_iter()
+            .map(|i| installed[i].clone())
+            .collect()
+    } else {
+        wanted
+    };
 
-            // Check if the output contains the port
-            output_str.contains(&format!(":{} ", port))
+    for addon in &selected {
+        if !installed.contains(addon) {
+            fail(&format!("Addon '{}' is not in custom_addons", addon));
         }
-        Err(_) => {
-            // If the command fails (e.g., `ss` is not installed), assume the port is available
-            println!(
-                "Warning: 'ss' command failed. Assuming port {} is available.",
-                port
-            );
-            false
+    }
+
+    for addon in &selected {
+        fs::remove_dir_all(project_dir.join("custom_addons").join(addon))
+            .unwrap_or_else(|e| fail(&format!("Failed to remove {}: {}", addon, e)));
+        println!("Removed addon: {}", addon);
+    }
+    let mut lock = Lockfile::load(project_dir).unwrap_or_else(|e| fail(&e));
+    for addon in &selected {
+        lock.remove(addon);
+    }
+    lock.save(project_dir).unwrap_or_else(|e| fail(&e));
+    project.addons.retain(|addon| !selected.contains(addon));
+    project.save(project_dir).unwrap_or_else(|e| fail(&e));
+    registry::record(project_dir, &project).unwrap_or_else(|e| fail(&e));
+}
+
+// List the copied addons that differ from the addon sources
+fn outdated_addons(project_dir: &Path, pull: Option<bool>) {
+    let project = Project::load(project_dir).unwrap_or_else(|e| fail(&e));
+    let config = Config::load().unwrap_or_else(|e| fail(&e));
+    let pack = Pack::load(&config.sources, &project.odoo_version, pull, false)
+        .unwrap_or_else(|e| fail(&e));
+
+    let statuses = addons::check_locked(&pack, project_dir).unwrap_or_else(|e| fail(&e));
+    let mut up_to_date = true;
+    for status in &statuses {
+        if !status.outdated() && status.latest.is_some() && !status.locally_modified {
+            continue;
+        }
+        up_to_date = false;
+        println!("{}", status.describe());
+        if status.outdated() {
+            for change in &status.changes {
+                println!("    {}", change);
+            }
         }
     }
+    if up_to_date {
+        println!("All {} copied addons are up to date.", statuses.len());
+    }
 }
 
-// Function to find an available port
-fn find_available_port(start_port: u16) -> u16 {
-    let mut port = start_port;
-    while is_port_in_use(port) {
+// Copy the current version of addons from the addon sources over the
+// project's copies
+fn update_addons(project_dir: &Path, wanted: Vec<String>, pull: Option<bool>, force: bool) {
+    let mut project = Project::load(project_dir).unwrap_or_else(|e| fail(&e));
+    let config = Config::load().unwrap_or_else(|e| fail(&e));
+    let pack = Pack::load(&config.sources, &project.odoo_version, pull, false)
+        .unwrap_or_else(|e| fail(&e));
+
+    let statuses = addons::check_locked(&pack, project_dir).unwrap_or_else(|e| fail(&e));
+    let outdated: Vec<&AddonStatus> = statuses.iter().filter(|status| status.outdated()).collect();
+    let selected: Vec<&AddonStatus> = if wanted.is_empty() {
+        if outdated.is_empty() {
+            println!("All copied addons are up to date.");
+            return;
+        }
+        let items: Vec<String> = outdated.iter().map(|status| status.describe()).collect();
+        MultiSelect::new()
+            .with_prompt("Select addons to update")
+            .items(&items)
+            .interact()
+            .expect("Failed to select addons")
+            .into_iter()
+            .map(|i| outdated[i])
+            .collect()
+    } else {
+        wanted
+            .iter()
+            .map(
+                |name| match statuses.iter().find(|status| &status.locked.name == name) {
+                    Some(status) if status.latest.is_some() => status,
+                    Some(_) => fail(&format!("Addon '{}' is not in the addon sources", name)),
+                    None => fail(&format!(
+                        "Addon '{}' was not copied from the addon sources",
+                        name
+                    )),
+                },
+            )
+            .collect()
+    };
+
+    let installed = addons::installed_addons(project_dir);
+    for status in selected {
+        let name = &status.locked.name;
+        if !status.outdated() {
+            println!("{} is up to date.", name);
+            continue;
+        }
+        if status.locally_modified && !force {
+            eprintln!(
+                "Warning: {} was modified locally, updating it loses the changes:",
+                name
+            );
+            for change in &status.changes {
+                eprintln!("    {}", change);
+            }
+            let confirmed = Confirm::new()
+                .with_prompt(format!("Overwrite {}?", name))
+                .default(false)
+                .interact()
+                .unwrap_or(false);
+            if !confirmed {
+                println!("Skipped {}", name);
+                continue;
+            }
+        }
+        addons::update_addon(&pack, name, project_dir).unwrap_or_else(|e| fail(&e));
+        let version = status
+            .latest
+            .as_ref()
+            .and_then(|latest| latest.version.as_deref());
         println!(
-            "Port {} is already in use. Trying port {}...",
-            port,
-            port + 1
+            "Updated {} to {}",
+            name,
+            version.unwrap_or("the source version")
         );
-        port += 1;
+
+        if let Ok(manifest) = &pack.addons[name].manifest {
+            for dependency in &manifest.depends {
+                if pack.addons.contains_key(dependency) && !installed.contains(dependency) {
+                    eprintln!(
+                        "Warning: {} depends on {}, which is not in the project: add it with `add-addon {}`",
+                        name, dependency, dependency
+                    );
+                }
+            }
+        }
+        if !project.addons.contains(name) {
+            project.addons.push(name.clone());
+        }
     }
-    port
+    warn_if_image_outdated(project_dir, &project);
+    project.save(project_dir).unwrap_or_else(|e| fail(&e));
+    registry::record(project_dir, &project).unwrap_or_else(|e| fail(&e));
 }
 
-// Helper function to copy directories or groups
-fn copy_dir_all(src: &str, dst: &str) -> std::io::Result<()> {
-    // Ensure the destination directory exists
-    fs::create_dir_all(dst)?;
+// Generate a new addon in the project's custom_addons folder
+fn new_addon(project_dir: &Path, name: &str, sample: bool) {
+    let project = Project::load(project_dir).unwrap_or_else(|e| fail(&e));
+    scaffold::validate_name(name).unwrap_or_else(|e| fail(&e));
+    let addon_dir = project_dir.join("custom_addons").join(name);
+    if addon_dir.exists() {
+        fail(&format!("{} already exists", addon_dir.display()));
+    }
 
-    // Check if the source directory contains __manifest__.py
-    let manifest_path = Path::new(src).join("__manifest__.py");
+    scaffold::addon_plan(&addon_dir, name, &project.odoo_version, sample)
+        .execute_into(&addon_dir)
+        .unwrap_or_else(|e| fail(&e.to_string()));
+    println!(
+        "Addon '{}' for Odoo {} has been created in {}",
+        name,
+        project.odoo_version,
+        addon_dir.display()
+    );
+}
 
-    if manifest_path.exists() {
-        // If __manifest__.py exists, treat it as a single addon
-        println!("Copying single addon: {}", src);
+// Templates saved with `new --save-template`
+fn list_templates() {
+    let names = template::names().unwrap_or_else(|e| fail(&e));
+    if names.is_empty() {
+        println!(
+            "No templates in {} yet, save one with `new --save-template <name>`.",
+            template::templates_dir().display()
+        );
+        return;
+    }
+    for name in names {
+        println!("{}", name);
+    }
+}
+
+// Print every registered project
+fn list_projects() {
+    let registry = Registry::load().unwrap_or_else(|e| fail(&e));
+    if registry.projects.is_empty() {
+        println!("No projects have been created yet.");
+        return;
+    }
 
-        let dest_path = format!(
-            "{}/{}",
-            dst,
-            Path::new(src).file_name().unwrap().to_string_lossy()
+    println!(
+        "{:<20} {:<6} {:<9} {:<12} {:<17} PATH",
+        "NAME", "ODOO", "POSTGRES", "PORTS", "CREATED"
+    );
+    for entry in &registry.projects {
+        let project = &entry.project;
+        let missing = if entry.path.exists() {
+            ""
+        } else {
+            " (missing)"
+        };
+        println!(
+            "{:<20} {:<6} {:<9} {:<12} {:<17} {}{}",
+            project.name,
+            project.odoo_version,
+            project.postgres_version,
+            format!("{}/{}", project.port, project.longpolling_port),
+            entry.created.format("%Y-%m-%d %H:%M"),
+            entry.path.display(),
+            missing
         );
-        // Copy the entire directory as-is to custom_addons
-        copy_dir_contents(src, &dest_path)?;
-    } else {
-        // If __manifest__.py doesn't exist, treat it as a group
-        println!("Treating {} as a group of addons", src);
-
-        // Iterate over all immediate contents of the group
-        for entry in fs::read_dir(src)? {
-            let entry = entry?; // Handle potential errors in reading the entry
-            let entry_path = entry.path(); // Get the full path of the entry
-            let entry_name = entry.file_name().to_string_lossy().into_owned(); // Get the name of the entry
-
-            // Create the destination path in custom_addons
-            let dest_path = format!("{}/{}", dst, entry_name);
-
-            // If it's a directory, copy it recursively
-            if entry_path.is_dir() {
-                fs::create_dir_all(&dest_path)?;
-                copy_dir_contents(entry_path.to_str().unwrap(), &dest_path)?;
-            } else {
-                // If it's a file, copy it directly
-                fs::copy(&entry_path, &dest_path)?;
-            }
+        if !project.addons.is_empty() {
+            println!("    addons: {}", project.addons.join(", "));
         }
     }
+}
 
-    Ok(())
+fn manage_database(command: DbCommand) {
+    match command {
+        DbCommand::Create {
+            project,
+            name,
+            lang,
+            demo,
+        } => {
+            db::create(&project.path, &name, lang.as_deref(), demo).unwrap_or_else(|e| fail(&e));
+            println!("Database '{}' has been created.", name);
+        }
+        DbCommand::Drop { project, name, yes } => {
+            let confirmed = yes
+                || ask_yes_no(&format!(
+                    "Delete the database '{}' with its filestore?",
+                    name
+                ));
+            if confirmed {
+                db::drop(&project.path, &name).unwrap_or_else(|e| fail(&e));
+                println!("Database '{}' has been dropped.", name);
+            }
+        }
+        DbCommand::Backup {
+            project,
+            name,
+            output,
+        } => {
+            let output = output.unwrap_or_else(|| {
+                PathBuf::from(format!(
+                    "{}_{}.zip",
+                    name,
+                    chrono::Local::now().format("%Y-%m-%d_%H-%M-%S")
+                ))
+            });
+            db::backup(&project.path, &name, &output).unwrap_or_else(|e| fail(&e));
+            println!("Database '{}' saved to {}", name, output.display());
+        }
+        DbCommand::Restore {
+            project,
+            name,
+            file,
+        } => {
+            db::restore(&project.path, &name, &file).unwrap_or_else(|e| fail(&e));
+            println!("{} restored into database '{}'.", file.display(), name);
+        }
+    }
 }
 
-// Helper function to copy all contents of a directory
-fn copy_dir_contents(src: &str, dst: &str) -> std::io::Result<()> {
-    for entry in fs::read_dir(src)? {
-        let entry = entry?; // Handle potential errors in reading the entry
-        let entry_path = entry.path(); // Get the full path of the entry
-        let entry_name = entry.file_name().to_string_lossy().into_owned(); // Get the name of the entry
+// Remove the containers, the project directory and the registry entry
+fn destroy_project(project_dir: &Path, yes: bool) {
+    let mut registry = Registry::load().unwrap_or_else(|e| fail(&e));
+    let path: PathBuf = match registry.find(project_dir) {
+        Some(entry) => entry.path.clone(),
+        None => fail(&format!(
+            "{} is not a registered project",
+            project_dir.display()
+        )),
+    };
 
-        // Create the destination path
-        let dest_path = format!("{}/{}", dst, entry_name);
+    let confirmed = yes
+        || ask_yes_no(&format!(
+            "Delete {} with its containers, database and files?",
+            path.display()
+        ));
+    if !confirmed {
+        return;
+    }
 
-        // If it's a directory, copy it recursively
-        if entry_path.is_dir() {
-            fs::create_dir_all(&dest_path)?;
-            copy_dir_contents(entry_path.to_str().unwrap(), &dest_path)?;
-        } else {
-            // If it's a file, copy it directly
-            fs::copy(&entry_path, &dest_path)?;
+    let mut removed = true;
+    if path.exists() {
+        // The data folder belongs to the users of the containers (the
+        // database is owned by postgres with mode 0700), so it is emptied
+        // from a container before the project folder is removed
+        docker::compose(&path, &["stop"]).unwrap_or_else(|e| fail(&e));
+        let empty_data = [
+            "run",
+            "--rm",
+            "--no-deps",
+            "--volume",
+            "./data:/mnt/data",
+            "--entrypoint",
+            "find",
+            compose::DB_SERVICE,
+            "/mnt/data",
+            "-mindepth",
+            "1",
+            "-delete",
+        ];
+        if let Err(e) = docker::compose(&path, &empty_data) {
+            eprintln!("Warning: {}", e);
         }
+        docker::compose(&path, &["down", "--volumes", "--remove-orphans"])
+            .unwrap_or_else(|e| fail(&e));
+        if let Err(e) = fs::remove_dir_all(&path) {
+            eprintln!("Warning: Failed to remove {}: {}", path.display(), e);
+            removed = false;
+        }
+    }
+    registry.forget(&path);
+    registry.save().unwrap_or_else(|e| fail(&e));
+    if removed {
+        println!("Project {} has been destroyed.", path.display());
+    } else {
+        println!(
+            "Project {} has been forgotten, but what is left of its folder must be deleted by hand (e.g. with sudo rm -rf).",
+            path.display()
+        );
     }
+}
+
+// Yes/No prompt, defaulting to Yes
+fn ask_yes_no(prompt: &str) -> bool {
+    Select::new()
+        .with_prompt(prompt)
+        .default(0)
+        .items(&["Yes", "No"])
+        .interact()
+        .unwrap()
+        == 0
+}
 
-    Ok(())
+// Print an error and exit
+fn fail(message: &str) -> ! {
+    eprintln!("Error: {}", message);
+    process::exit(1);
 }
@@ -0,0 +1,381 @@
+use std::fs;
+use std::path::Path;
+
+// The parts of an addon's __manifest__.py the tool cares about
+#[derive(Debug, Clone)]
+pub struct Manifest {
+    pub name: String,
+    pub version: Option<String>,
+    pub depends: Vec<String>,
+    pub external_dependencies: ExternalDependencies,
+    pub installable: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ExternalDependencies {
+    pub python: Vec<String>,
+    pub bin: Vec<String>,
+}
+
+impl Manifest {
+    pub fn load(addon_dir: &Path) -> Result<Manifest, String> {
+        let path = addon_dir.join("__manifest__.py");
+        let source = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        Manifest::parse(&source).map_err(|e| format!("Invalid {}: {}", path.display(), e))
+    }
+
+    pub fn parse(source: &str) -> Result<Manifest, String> {
+        let mut parser = Parser::new(source);
+        let value = parser.value()?;
+        let PyValue::Dict(entries) = value else {
+            return Err("the manifest is not a dictionary".to_string());
+        };
+        let get = |key: &str| {
+            entries
+                .iter()
+                .find(|(k, _)| matches!(k, PyValue::Str(s) if s == key))
+                .map(|(_, v)| v)
+        };
+
+        let external_dependencies = match get("external_dependencies") {
+            Some(PyValue::Dict(deps)) => {
+                let list = |key: &str| {
+                    deps.iter()
+                        .find(|(k, _)| matches!(k, PyValue::Str(s) if s == key))
+                        .map(|(_, v)| string_list(v, key))
+                        .unwrap_or(Ok(Vec::new()))
+                };
+                ExternalDependencies {
+                    python: list("python")?,
+                    bin: list("bin")?,
+                }
+            }
+            Some(_) => return Err("external_dependencies must be a dictionary".to_string()),
+            None => ExternalDependencies::default(),
+        };
+
+        Ok(Manifest {
+            name: match get("name") {
+                Some(PyValue::Str(name)) => name.clone(),
+                Some(_) => return Err("name must be a string".to_string()),
+                None => String::new(),
+            },
+            version: match get("version") {
+                Some(PyValue::Str(version)) | Some(PyValue::Num(version)) => Some(version.clone()),
+                Some(_) => return Err("version must be a string".to_string()),
+                None => None,
+            },
+            depends: match get("depends") {
+                Some(depends) => string_list(depends, "depends")?,
+                None => Vec::new(),
+            },
+            external_dependencies,
+            installable: match get("installable") {
+                Some(PyValue::Bool(installable)) => *installable,
+                Some(_) => return Err("installable must be True or False".to_string()),
+                None => true,
+            },
+        })
+    }
+
+    // Versions follow `<series>.<major>.<minor>.<patch>` (e.g. 17.0.1.0.0);
+    // short versions like 1.0 don't name a series and are accepted everywhere
+    pub fn supports(&self, odoo_version: &str) -> bool {
+        match &self.version {
+            Some(version) => {
+                let parts: Vec<&str> = version.split('.').collect();
+                parts.len() < 5
+                    || format!("{}.{}", parts[0], parts[1]) == format!("{}.0", odoo_version)
+            }
+            None => true,
+        }
+    }
+}
+
+fn string_list(value: &PyValue, key: &str) -> Result<Vec<String>, String> {
+    match value {
+        PyValue::List(items) => items
+            .iter()
+            .map(|item| match item {
+                PyValue::Str(s) => Ok(s.clone()),
+                _ => Err(format!("{} must only contain strings", key)),
+            })
+            .collect(),
+        _ => Err(format!("{} must be a list", key)),
+    }
+}
+
+// The Python literals that can appear in a manifest
+#[derive(Debug)]
+enum PyValue {
+    Str(String),
+    Num(String),
+    Bool(bool),
+    None,
+    List(Vec<PyValue>),
+    Dict(Vec<(PyValue, PyValue)>),
+}
+
+// Minimal parser for the Python literal syntax used in manifests
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn new(source: &str) -> Parser {
+        Parser {
+            chars: source.chars().collect(),
+            pos: 0,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn line(&self) -> usize {
+        self.chars[..self.pos]
+            .iter()
+            .filter(|c| **c == '\n')
+            .count()
+            + 1
+    }
+
+    fn error(&self, message: &str) -> String {
+        format!("{} at line {}", message, self.line())
+    }
+
+    // Skip whitespace and comments
+    fn skip(&mut self) {
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() {
+                self.pos += 1;
+            } else if c == '#' {
+                while !matches!(self.peek(), None | Some('\n')) {
+                    self.pos += 1;
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        self.skip();
+        if self.peek() == Some(expected) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{}'", expected)))
+        }
+    }
+
+    fn value(&mut self) -> Result<PyValue, String> {
+        self.skip();
+        match self.peek() {
+            Some('{') => self.dict(),
+            Some('[') => self.sequence(']').map(PyValue::List),
+            Some('(') => self.sequence(')').map(PyValue::List),
+            Some('\'') | Some('"') => self.strings(),
+            Some(c) if c.is_ascii_digit() || c == '-' || c == '+' || c == '.' => Ok(self.number()),
+            Some(c) if c.is_alphabetic() || c == '_' => {
+                let word = self.word();
+                match word.as_str() {
+                    "True" => Ok(PyValue::Bool(true)),
+                    "False" => Ok(PyValue::Bool(false)),
+                    "None" => Ok(PyValue::None),
+                    // String prefixes such as r'' or u''
+                    "r" | "u" | "b" | "R" | "U" | "B"
+                        if matches!(self.peek(), Some('\'') | Some('"')) =>
+                    {
+                        self.strings()
+                    }
+                    _ => Err(self.error(&format!("unsupported expression '{}'", word))),
+                }
+            }
+            Some(c) => Err(self.error(&format!("unexpected character '{}'", c))),
+            None => Err(self.error("unexpected end of file")),
+        }
+    }
+
+    fn dict(&mut self) -> Result<PyValue, String> {
+        self.expect('{')?;
+        let mut entries = Vec::new();
+        loop {
+            self.skip();
+            if self.peek() == Some('}') {
+                self.pos += 1;
+                return Ok(PyValue::Dict(entries));
+            }
+            let key = self.value()?;
+            self.expect(':')?;
+            let value = self.value()?;
+            entries.push((key, value));
+            self.skip();
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some('}') => {}
+                _ => return Err(self.error("expected ',' or '}'")),
+            }
+        }
+    }
+
+    fn sequence(&mut self, close: char) -> Result<Vec<PyValue>, String> {
+        self.pos += 1;
+        let mut items = Vec::new();
+        loop {
+            self.skip();
+            if self.peek() == Some(close) {
+                self.pos += 1;
+                return Ok(items);
+            }
+            items.push(self.value()?);
+            self.skip();
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some(c) if c == close => {}
+                _ => return Err(self.error(&format!("expected ',' or '{}'", close))),
+            }
+        }
+    }
+
+    // Adjacent string literals are concatenated, as in Python
+    fn strings(&mut self) -> Result<PyValue, String> {
+        let mut value = self.string()?;
+        loop {
+            self.skip();
+            match self.peek() {
+                Some('\'') | Some('"') => value.push_str(&self.string()?),
+                _ => return Ok(PyValue::Str(value)),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let quote = self.peek().unwrap();
+        let triple = self.chars[self.pos..].starts_with(&[quote, quote, quote]);
+        self.pos += if triple { 3 } else { 1 };
+
+        let mut value = String::new();
+        loop {
+            match self.peek() {
+                None => return Err(self.error("unterminated string")),
+                Some('\\') => {
+                    self.pos += 1;
+                    let escaped = self
+                        .peek()
+                        .ok_or_else(|| self.error("unterminated string"))?;
+                    match escaped {
+                        'n' => value.push('\n'),
+                        't' => value.push('\t'),
+                        '\n' => {}
+                        '\\' | '\'' | '"' => value.push(escaped),
+                        other => {
+                            value.push('\\');
+                            value.push(other);
+                        }
+                    }
+                    self.pos += 1;
+                }
+                Some(c) if c == quote => {
+                    if !triple {
+                        self.pos += 1;
+                        return Ok(value);
+                    }
+                    if self.chars[self.pos..].starts_with(&[quote, quote, quote]) {
+                        self.pos += 3;
+                        return Ok(value);
+                    }
+                    value.push(c);
+                    self.pos += 1;
+                }
+                Some('\n') if !triple => return Err(self.error("unterminated string")),
+                Some(c) => {
+                    value.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+    }
+
+    fn number(&mut self) -> PyValue {
+        let start = self.pos;
+        while matches!(self.peek(), Some(c) if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '+' | '_'))
+        {
+            self.pos += 1;
+        }
+        PyValue::Num(self.chars[start..self.pos].iter().collect())
+    }
+
+    fn word(&mut self) -> String {
+        let start = self.pos;
+        while matches!(self.peek(), Some(c) if c.is_alphanumeric() || c == '_') {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_python_literals() {
+        let manifest = Manifest::parse(
+            r#"# -*- coding: utf-8 -*-
+{
+    # The name shown in the apps
+    'name': u'Sale ' "Extra",  # adjacent strings
+    "version": '17.0.1.2.0',
+    'summary': """Triple "quoted"
+summary""",
+    'description': r'C:\addons',
+    'depends': ('sale', 'stock',),
+    'external_dependencies': {'python': ['ldap'], 'bin': []},
+    'data': [
+        'views/sale.xml',
+    ],
+    'application': True,
+    'auto_install': None,
+}
+"#,
+        )
+        .unwrap();
+        assert_eq!(manifest.name, "Sale Extra");
+        assert_eq!(manifest.version.as_deref(), Some("17.0.1.2.0"));
+        assert_eq!(manifest.depends, ["sale", "stock"]);
+        assert_eq!(manifest.external_dependencies.python, ["ldap"]);
+        assert!(manifest.external_dependencies.bin.is_empty());
+        assert!(manifest.installable);
+    }
+
+    #[test]
+    fn reads_installable() {
+        let manifest = Manifest::parse("{'name': 'Old', 'installable': False}").unwrap();
+        assert!(!manifest.installable);
+        assert!(Manifest::parse("{'installable': 'no'}").is_err());
+    }
+
+    #[test]
+    fn rejects_what_is_not_a_dictionary() {
+        assert!(Manifest::parse("['name']").is_err());
+        assert!(Manifest::parse("{'name': 'Unterminated}").is_err());
+        assert!(Manifest::parse("{'depends': 'sale'}").is_err());
+        assert!(Manifest::parse("{'name': 'Computed', 'version': VERSION}").is_err());
+    }
+
+    #[test]
+    fn supports_the_series_of_the_version() {
+        let manifest = |version: &str| Manifest::parse(&format!("{{'version': '{}'}}", version));
+        assert!(manifest("17.0.1.0.0").unwrap().supports("17"));
+        assert!(!manifest("16.0.1.0.0").unwrap().supports("17"));
+        assert!(manifest("1.0").unwrap().supports("17"));
+        assert!(manifest("2.1.3").unwrap().supports("16"));
+        assert!(Manifest::parse("{'name': 'Unversioned'}")
+            .unwrap()
+            .supports("17"));
+    }
+}
@@ -0,0 +1,302 @@
+use crate::ports::{HTTP_PORT, LONGPOLLING_PORT};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+// Server tuning presets offered by the wizard
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Preset {
+    #[default]
+    Dev,
+    Staging,
+    Production,
+}
+
+impl Preset {
+    pub const ALL: [Preset; 3] = [Preset::Dev, Preset::Staging, Preset::Production];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Preset::Dev => "dev",
+            Preset::Staging => "staging",
+            Preset::Production => "production",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Smtp {
+    pub server: String,
+    #[serde(default = "default_smtp_port")]
+    pub port: u16,
+    pub user: Option<String>,
+    pub password: Option<String>,
+    #[serde(default)]
+    pub ssl: bool,
+    pub email_from: Option<String>,
+}
+
+fn default_smtp_port() -> u16 {
+    25
+}
+
+// Typed model of config/odoo.conf
+#[derive(Debug, Clone)]
+pub struct OdooConfig {
+    pub addons_path: Vec<String>,
+    pub data_dir: String,
+    pub admin_passwd: String,
+    pub db_host: String,
+    pub db_port: u16,
+    pub db_user: String,
+    pub db_password: String,
+    pub list_db: bool,
+    pub proxy_mode: bool,
+    pub workers: u32,
+    pub max_cron_threads: u32,
+    pub limit_memory_soft: u64,
+    pub limit_memory_hard: u64,
+    pub limit_time_cpu: u32,
+    pub limit_time_real: u32,
+    pub limit_time_real_cron: i32,
+    pub limit_request: u32,
+    pub log_level: String,
+    pub log_handler: Vec<String>,
+    // Port of the longpolling/websocket worker inside the container
+    pub longpolling_port: u16,
+    pub smtp: Option<Smtp>,
+}
+
+const MB: u64 = 1024 * 1024;
+
+impl OdooConfig {
+    // Settings of `preset` for the generated docker stack
+    pub fn preset(preset: Preset, admin_passwd: &str, db_password: &str) -> OdooConfig {
+        let config = OdooConfig {
+            addons_path: vec![
+                "/mnt/extra-addons".to_string(),
+                "/mnt/custom-addons".to_string(),
+            ],
+            data_dir: "/var/lib/odoo".to_string(),
+            admin_passwd: admin_passwd.to_string(),
+            db_host: "postgres".to_string(),
+            db_port: 5432,
+            db_user: "odoo".to_string(),
+            db_password: db_password.to_string(),
+            list_db: true,
+            proxy_mode: false,
+            workers: 0,
+            max_cron_threads: 1,
+            limit_memory_soft: 2048 * MB,
+            limit_memory_hard: 2560 * MB,
+            limit_time_cpu: 600,
+            limit_time_real: 1200,
+            limit_time_real_cron: -1,
+            limit_request: 8192,
+            log_level: "info".to_string(),
+            log_handler: vec![":INFO".to_string()],
+            longpolling_port: LONGPOLLING_PORT,
+            smtp: None,
+        };
+
+        match preset {
+            Preset::Dev => OdooConfig {
+                log_level: "debug".to_string(),
+                ..config
+            },
+            Preset::Staging => OdooConfig {
+                proxy_mode: true,
+                workers: 2,
+                ..config
+            },
+            Preset::Production => OdooConfig {
+                proxy_mode: true,
+                list_db: false,
+                workers: 4,
+                max_cron_threads: 2,
+                limit_memory_soft: 1536 * MB,
+                limit_memory_hard: 2048 * MB,
+                limit_time_cpu: 120,
+                limit_time_real: 240,
+                limit_time_real_cron: 600,
+                log_level: "warn".to_string(),
+                log_handler: vec![":WARNING".to_string(), "odoo.addons:INFO".to_string()],
+                ..config
+            },
+        }
+    }
+
+    // Port serving the longpolling/websocket bus: the gevent worker only runs
+    // in multi-process mode, otherwise the HTTP server answers it too
+    pub fn bus_port(&self) -> u16 {
+        if self.workers > 0 {
+            self.longpolling_port
+        } else {
+            HTTP_PORT
+        }
+    }
+
+    // The odoo.conf contents. Odoo 16 renamed longpolling_port to gevent_port.
+    pub fn render(&self, odoo_version: &str) -> String {
+        let major: u32 = odoo_version.parse().unwrap_or(0);
+        let gevent_key = if major >= 16 {
+            "gevent_port"
+        } else {
+            "longpolling_port"
+        };
+
+        let mut options: Vec<(&str, String)> = vec![
+            ("addons_path", self.addons_path.join(",")),
+            ("data_dir", self.data_dir.clone()),
+            ("admin_passwd", self.admin_passwd.clone()),
+            ("db_host", self.db_host.clone()),
+            ("db_port", self.db_port.to_string()),
+            ("db_user", self.db_user.clone()),
+            ("db_password", self.db_password.clone()),
+        ];
+        options.extend([
+            ("list_db", python_bool(self.list_db)),
+            ("proxy_mode", python_bool(self.proxy_mode)),
+            ("workers", self.workers.to_string()),
+            ("max_cron_threads", self.max_cron_threads.to_string()),
+            (gevent_key, self.longpolling_port.to_string()),
+            ("limit_memory_soft", self.limit_memory_soft.to_string()),
+            ("limit_memory_hard", self.limit_memory_hard.to_string()),
+            ("limit_time_cpu", self.limit_time_cpu.to_string()),
+            ("limit_time_real", self.limit_time_real.to_string()),
+            (
+                "limit_time_real_cron",
+                self.limit_time_real_cron.to_string(),
+            ),
+            ("limit_request", self.limit_request.to_string()),
+            ("log_level", self.log_level.clone()),
+            ("log_handler", self.log_handler.join(",")),
+        ]);
+        if let Some(smtp) = &self.smtp {
+            options.push(("smtp_server", smtp.server.clone()));
+            options.push(("smtp_port", smtp.port.to_string()));
+            options.push(("smtp_ssl", python_bool(smtp.ssl)));
+            if let Some(user) = &smtp.user {
+                options.push(("smtp_user", user.clone()));
+            }
+            if let Some(password) = &smtp.password {
+                options.push(("smtp_password", password.clone()));
+            }
+            if let Some(email_from) = &smtp.email_from {
+                options.push(("email_from", email_from.clone()));
+            }
+        }
+
+        let mut content = String::from("[options]\n");
+        for (key, value) in options {
+            writeln!(content, "{} = {}", key, value).unwrap();
+        }
+        content
+    }
+}
+
+fn python_bool(value: bool) -> String {
+    if value { "True" } else { "False" }.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The value of `key` in a rendered odoo.conf
+    fn option(content: &str, key: &str) -> Option<String> {
+        content.lines().find_map(|line| {
+            let (k, v) = line.split_once(" = ")?;
+            (k == key).then(|| v.to_string())
+        })
+    }
+
+    #[test]
+    fn gevent_port_key_follows_the_odoo_version() {
+        let config = OdooConfig::preset(Preset::Dev, "admin", "secret");
+        let conf = config.render("15");
+        assert_eq!(option(&conf, "longpolling_port").as_deref(), Some("8072"));
+        assert_eq!(option(&conf, "gevent_port"), None);
+        for version in ["16", "17", "18"] {
+            let conf = config.render(version);
+            assert_eq!(option(&conf, "gevent_port").as_deref(), Some("8072"));
+            assert_eq!(option(&conf, "longpolling_port"), None);
+        }
+    }
+
+    #[test]
+    fn presets_tune_the_server() {
+        let render = |preset| OdooConfig::preset(preset, "admin", "secret").render("17");
+
+        let dev = render(Preset::Dev);
+        assert!(dev.starts_with("[options]\n"));
+        assert_eq!(option(&dev, "workers").as_deref(), Some("0"));
+        assert_eq!(option(&dev, "log_level").as_deref(), Some("debug"));
+        assert_eq!(option(&dev, "proxy_mode").as_deref(), Some("False"));
+        assert_eq!(option(&dev, "list_db").as_deref(), Some("True"));
+        assert_eq!(option(&dev, "admin_passwd").as_deref(), Some("admin"));
+        assert_eq!(option(&dev, "db_password").as_deref(), Some("secret"));
+        assert_eq!(
+            option(&dev, "addons_path").as_deref(),
+            Some("/mnt/extra-addons,/mnt/custom-addons")
+        );
+
+        let staging = render(Preset::Staging);
+        assert_eq!(option(&staging, "workers").as_deref(), Some("2"));
+        assert_eq!(option(&staging, "proxy_mode").as_deref(), Some("True"));
+        assert_eq!(option(&staging, "log_level").as_deref(), Some("info"));
+
+        let production = render(Preset::Production);
+        assert_eq!(option(&production, "workers").as_deref(), Some("4"));
+        assert_eq!(option(&production, "list_db").as_deref(), Some("False"));
+        assert_eq!(
+            option(&production, "max_cron_threads").as_deref(),
+            Some("2")
+        );
+        assert_eq!(
+            option(&production, "limit_memory_hard").as_deref(),
+            Some("2147483648")
+        );
+        assert_eq!(
+            option(&production, "log_handler").as_deref(),
+            Some(":WARNING,odoo.addons:INFO")
+        );
+        assert_eq!(option(&production, "smtp_server"), None);
+    }
+
+    #[test]
+    fn renders_the_smtp_server() {
+        let mut config = OdooConfig::preset(Preset::Production, "admin", "secret");
+        config.smtp = Some(Smtp {
+            server: "smtp.example.com".to_string(),
+            port: 587,
+            user: Some("odoo".to_string()),
+            password: None,
+            ssl: true,
+            email_from: Some("odoo@example.com".to_string()),
+        });
+        let conf = config.render("17");
+        assert_eq!(
+            option(&conf, "smtp_server").as_deref(),
+            Some("smtp.example.com")
+        );
+        assert_eq!(option(&conf, "smtp_port").as_deref(), Some("587"));
+        assert_eq!(option(&conf, "smtp_ssl").as_deref(), Some("True"));
+        assert_eq!(option(&conf, "smtp_user").as_deref(), Some("odoo"));
+        assert_eq!(option(&conf, "smtp_password"), None);
+        assert_eq!(
+            option(&conf, "email_from").as_deref(),
+            Some("odoo@example.com")
+        );
+    }
+
+    #[test]
+    fn bus_is_served_by_the_gevent_worker_only_with_workers() {
+        let dev = OdooConfig::preset(Preset::Dev, "admin", "secret");
+        assert_eq!(dev.bus_port(), HTTP_PORT);
+        let production = OdooConfig::preset(Preset::Production, "admin", "secret");
+        assert_eq!(production.bus_port(), LONGPOLLING_PORT);
+    }
+}
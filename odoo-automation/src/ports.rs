@@ -0,0 +1,82 @@
+use std::io::ErrorKind;
+use std::net::{Ipv4Addr, Ipv6Addr, TcpListener};
+
+// Ports used inside the Odoo container
+pub const HTTP_PORT: u16 = 8069;
+pub const LONGPOLLING_PORT: u16 = 8072;
+// First host port tried for the HTTPS proxy
+pub const HTTPS_PORT: u16 = 8443;
+
+// How many ports to try before giving up
+const MAX_TRIES: u16 = 100;
+
+// A port is in use when something already listens on it, on IPv4 or IPv6
+pub fn is_port_in_use(port: u16) -> bool {
+    // Each listener is dropped right away, so the IPv6 probe doesn't collide
+    // with the IPv4 one on dual-stack hosts
+    let in_use = |result: std::io::Result<TcpListener>| matches!(result, Err(e) if e.kind() == ErrorKind::AddrInUse);
+    in_use(TcpListener::bind((Ipv4Addr::UNSPECIFIED, port)))
+        || in_use(TcpListener::bind((Ipv6Addr::UNSPECIFIED, port)))
+}
+
+// Find the first port from `start_port` that is neither in use nor in `claimed`
+pub fn find_available_port(start_port: u16, claimed: &[u16]) -> Result<u16, String> {
+    let end_port = start_port.saturating_add(MAX_TRIES - 1);
+    for port in start_port..=end_port {
+        if claimed.contains(&port) {
+            println!(
+                "Port {} is claimed by another project. Trying the next one...",
+                port
+            );
+        } else if is_port_in_use(port) {
+            println!("Port {} is already in use. Trying the next one...", port);
+        } else {
+            return Ok(port);
+        }
+    }
+    Err(format!(
+        "No available port between {} and {}",
+        start_port, end_port
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ports_with_a_listener_are_skipped() {
+        let listener = TcpListener::bind((Ipv4Addr::UNSPECIFIED, 0)).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        assert!(is_port_in_use(port));
+        let found = find_available_port(port, &[]).unwrap();
+        assert!(found > port);
+        assert!(!is_port_in_use(found));
+    }
+
+    #[test]
+    fn claimed_ports_are_skipped() {
+        let port = TcpListener::bind((Ipv4Addr::UNSPECIFIED, 0))
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port()
+            .min(u16::MAX - MAX_TRIES);
+        let found = find_available_port(port, &[port, port + 1]).unwrap();
+        assert!(found >= port + 2);
+    }
+
+    #[test]
+    fn search_stops_after_max_tries() {
+        let claimed: Vec<u16> = (20000..20000 + MAX_TRIES).collect();
+        assert_eq!(
+            find_available_port(20000, &claimed),
+            Err("No available port between 20000 and 20099".to_string())
+        );
+        // Never past the last port
+        assert_eq!(
+            find_available_port(u16::MAX, &[u16::MAX]),
+            Err("No available port between 65535 and 65535".to_string())
+        );
+    }
+}
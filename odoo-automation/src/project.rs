@@ -0,0 +1,218 @@
+use crate::odoo_conf::Preset;
+use crate::proxy::Proxy;
+use crate::services::ExtraService;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Metadata file written in every generated project
+pub const PROJECT_FILE: &str = "odoo-project.yml";
+
+// Characters a shell would interpret
+const SHELL_METACHARACTERS: &str = " \t\n$`'\"\\;&|<>()[]{}*?!~#";
+
+// The project name is used as the directory name and as the docker compose
+// project name, which allows lowercase letters, digits, '-' and '_', starting
+// with a letter or a digit
+pub fn validate_name(name: &str) -> Result<(), String> {
+    let problem = if name.is_empty() {
+        "it is empty".to_string()
+    } else if name.contains('/') {
+        "it contains a path separator".to_string()
+    } else if name.contains("..") {
+        "it contains '..'".to_string()
+    } else if let Some(c) = name.chars().find(|c| SHELL_METACHARACTERS.contains(*c)) {
+        format!("it contains the shell metacharacter {:?}", c)
+    } else if !name.starts_with(|c: char| c.is_ascii_lowercase() || c.is_ascii_digit()) {
+        "it must start with a lowercase letter or a digit".to_string()
+    } else if let Some(c) = name
+        .chars()
+        .find(|c| !(c.is_ascii_lowercase() || c.is_ascii_digit() || *c == '-' || *c == '_'))
+    {
+        format!(
+            "{:?} is not allowed, only lowercase letters, digits, '-' and '_' are",
+            c
+        )
+    } else {
+        return Ok(());
+    };
+    Err(format!(
+        "Invalid project name '{}': {} (try '{}')",
+        name,
+        problem,
+        slug(name)
+    ))
+}
+
+// A valid project name close to `name`, e.g. "My Shop 2" gives "my-shop-2"
+pub fn slug(name: &str) -> String {
+    let mut slug = String::new();
+    for c in name.chars().flat_map(char::to_lowercase) {
+        if c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-' {
+            slug.push(c);
+        } else if !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug = slug
+        .trim_start_matches(['-', '_'])
+        .trim_end_matches('-')
+        .to_string();
+    if slug.is_empty() {
+        "odoo-project".to_string()
+    } else {
+        slug
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Edition {
+    #[default]
+    Community,
+    Enterprise,
+}
+
+impl Edition {
+    pub const ALL: [Edition; 2] = [Edition::Community, Edition::Enterprise];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Edition::Community => "community",
+            Edition::Enterprise => "enterprise",
+        }
+    }
+}
+
+// What the wizard generated, so later commands can manage the project
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Project {
+    pub name: String,
+    pub odoo_version: String,
+    pub postgres_version: String,
+    pub port: u16,
+    #[serde(default)]
+    pub longpolling_port: u16,
+    #[serde(default)]
+    pub preset: Preset,
+    #[serde(default)]
+    pub edition: Edition,
+    // Local checkout of the enterprise addons, for the Enterprise edition
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enterprise_path: Option<PathBuf>,
+    // HTTPS reverse proxy, listening on `port` and `https_port`
+    #[serde(default)]
+    pub proxy: Proxy,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub domain: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub https_port: Option<u16>,
+    // Companion services, e.g. mailpit
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub services: Vec<ExtraService>,
+    #[serde(default)]
+    pub addons: Vec<String>,
+}
+
+impl Project {
+    pub fn load(project_dir: &Path) -> Result<Project, String> {
+        let path = project_dir.join(PROJECT_FILE);
+        let content = fs::read_to_string(&path).map_err(|e| {
+            format!(
+                "{} is not a project created by odoo-automation ({}: {})",
+                project_dir.display(),
+                PROJECT_FILE,
+                e
+            )
+        })?;
+        serde_yaml::from_str(&content).map_err(|e| format!("Invalid {}: {}", path.display(), e))
+    }
+
+    // Host ports the project publishes
+    pub fn ports(&self) -> Vec<u16> {
+        let mut ports = vec![self.port, self.longpolling_port];
+        ports.extend(self.https_port);
+        for service in &self.services {
+            ports.extend(&service.ports);
+        }
+        ports.retain(|port| *port != 0);
+        ports
+    }
+
+    // Where Odoo is reached from the host
+    pub fn url(&self) -> String {
+        match (&self.domain, self.https_port) {
+            (Some(domain), Some(443)) if self.proxy != Proxy::None => format!("https://{}", domain),
+            (Some(domain), Some(port)) if self.proxy != Proxy::None => {
+                format!("https://{}:{}", domain, port)
+            }
+            _ => format!("http://localhost:{}", self.port),
+        }
+    }
+
+    pub fn to_yaml(&self) -> Result<String, String> {
+        serde_yaml::to_string(self)
+            .map_err(|e| format!("Failed to serialize {}: {}", PROJECT_FILE, e))
+    }
+
+    pub fn save(&self, project_dir: &Path) -> Result<(), String> {
+        let path = project_dir.join(PROJECT_FILE);
+        let content = self.to_yaml()?;
+        fs::write(&path, content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_compose_project_names() {
+        for name in ["shop", "shop-2", "my_shop", "17-demo", "a"] {
+            assert_eq!(validate_name(name), Ok(()), "{}", name);
+        }
+    }
+
+    #[test]
+    fn rejects_paths() {
+        for name in ["", "a/b", "/shop", "..", "shop..old", "../shop"] {
+            assert!(validate_name(name).is_err(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn rejects_shell_metacharacters() {
+        for name in [
+            "my shop", "shop;rm", "$(id)", "shop`x`", "a|b", "a&b", "'a'", "a*",
+        ] {
+            let error = validate_name(name).unwrap_err();
+            assert!(error.contains("shell metacharacter"), "{}: {}", name, error);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_compose_project_names() {
+        for name in ["Shop", "-shop", "_shop", "shop.v2", "café"] {
+            assert!(validate_name(name).is_err(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn suggests_a_valid_slug() {
+        assert_eq!(slug("My Shop 2"), "my-shop-2");
+        assert_eq!(slug("../etc/passwd"), "etc-passwd");
+        assert_eq!(slug("  Odoo   17.0 "), "odoo-17-0");
+        assert_eq!(slug("_private_"), "private_");
+        assert_eq!(slug("$()"), "odoo-project");
+        for name in ["My Shop 2", "../etc/passwd", "café", "$()", "-x-"] {
+            assert_eq!(validate_name(&slug(name)), Ok(()), "{}", name);
+        }
+    }
+
+    #[test]
+    fn error_mentions_the_suggestion() {
+        let error = validate_name("My Shop").unwrap_err();
+        assert!(error.contains("'my-shop'"), "{}", error);
+    }
+}
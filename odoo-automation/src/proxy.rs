@@ -0,0 +1,354 @@
+use crate::compose::{ComposeFile, DependsOn, Service, NETWORK};
+use crate::odoo_conf::OdooConfig;
+use crate::ports::HTTP_PORT;
+use chrono::{Datelike, Days, Utc};
+use clap::ValueEnum;
+use rcgen::{CertificateParams, DnType, KeyPair};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+// Configuration templates of the proxies, with {{placeholders}}
+const NGINX_CONF: &str = include_str!("../data/proxy/nginx.conf");
+const TRAEFIK_CONF: &str = include_str!("../data/proxy/dynamic.yml");
+
+// Where the proxy configuration and the certificate go in the project
+pub const CERT_FILE: &str = "proxy/certs/cert.pem";
+pub const KEY_FILE: &str = "proxy/certs/key.pem";
+pub const NGINX_FILE: &str = "proxy/nginx.conf";
+// Not traefik.yml, which Traefik would read as its static configuration
+pub const TRAEFIK_FILE: &str = "proxy/dynamic.yml";
+
+// Reverse proxy serving Odoo over HTTPS
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Proxy {
+    #[default]
+    None,
+    Nginx,
+    Traefik,
+}
+
+impl Proxy {
+    pub const ALL: [Proxy; 3] = [Proxy::None, Proxy::Nginx, Proxy::Traefik];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Proxy::None => "none",
+            Proxy::Nginx => "nginx",
+            Proxy::Traefik => "traefik",
+        }
+    }
+}
+
+// Host names the certificate can be made for, e.g. shop.localhost
+pub fn validate_domain(domain: &str) -> Result<(), String> {
+    let valid = !domain.is_empty()
+        && domain.split('.').all(|label| {
+            !label.is_empty()
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        });
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("Invalid domain name '{}'", domain))
+    }
+}
+
+// <name>.localhost, with the characters project names allow but host names
+// don't replaced
+pub fn default_domain(project_name: &str) -> String {
+    let label = project_name.replace('_', "-");
+    format!("{}.localhost", label.trim_end_matches('-'))
+}
+
+// Odoo 16 replaced the longpolling bus with a websocket
+pub fn bus_path(odoo_version: &str) -> &'static str {
+    if odoo_version.parse().unwrap_or(0) >= 16 {
+        "/websocket"
+    } else {
+        "/longpolling"
+    }
+}
+
+// Self-signed certificate and private key, in PEM
+#[derive(Debug, Clone, PartialEq)]
+pub struct Certificate {
+    pub cert: String,
+    pub key: String,
+}
+
+impl Certificate {
+    pub fn self_signed(domain: &str) -> Result<Certificate, String> {
+        let error =
+            |e: rcgen::Error| format!("Failed to generate a certificate for {}: {}", domain, e);
+        let mut params = CertificateParams::new(vec![domain.to_string()]).map_err(error)?;
+        params
+            .distinguished_name
+            .push(DnType::CommonName, domain.to_string());
+        // Browsers refuse certificates valid for more than 825 days
+        let today = Utc::now().date_naive();
+        let expires = today + Days::new(825);
+        params.not_before =
+            rcgen::date_time_ymd(today.year(), today.month() as u8, today.day() as u8);
+        params.not_after =
+            rcgen::date_time_ymd(expires.year(), expires.month() as u8, expires.day() as u8);
+        let key = KeyPair::generate().map_err(error)?;
+        let cert = params.self_signed(&key).map_err(error)?;
+        Ok(Certificate {
+            cert: cert.pem(),
+            key: key.serialize_pem(),
+        })
+    }
+
+    // The certificate of an existing project
+    pub fn read(project_dir: &Path) -> Option<Certificate> {
+        Some(Certificate {
+            cert: fs::read_to_string(project_dir.join(CERT_FILE)).ok()?,
+            key: fs::read_to_string(project_dir.join(KEY_FILE)).ok()?,
+        })
+    }
+}
+
+// nginx site forwarding the bus path to the gevent worker of `config`, if
+// any, and everything else to the HTTP workers
+pub fn nginx_conf(
+    domain: &str,
+    https_port: u16,
+    odoo_version: &str,
+    config: &OdooConfig,
+) -> String {
+    let https_suffix = if https_port == 443 {
+        String::new()
+    } else {
+        format!(":{}", https_port)
+    };
+    let vars = [
+        ("domain", domain.to_string()),
+        ("https_suffix", https_suffix),
+        ("bus_path", bus_path(odoo_version).to_string()),
+        ("http_port", HTTP_PORT.to_string()),
+        ("bus_port", config.bus_port().to_string()),
+    ];
+    vars.iter()
+        .fold(NGINX_CONF.to_string(), |text, (key, value)| {
+            text.replace(&format!("{{{{{}}}}}", key), value)
+        })
+}
+
+pub fn traefik_conf() -> String {
+    TRAEFIK_CONF.to_string()
+}
+
+// Put `proxy` in front of the web service: Odoo is no longer published on the
+// host, the proxy listens on `http_port` (redirecting to HTTPS) and `https_port`
+#[allow(clippy::too_many_arguments)]
+pub fn add_to(
+    compose: &mut ComposeFile,
+    proxy: Proxy,
+    project_name: &str,
+    domain: &str,
+    http_port: u16,
+    https_port: u16,
+    odoo_version: &str,
+    config: &OdooConfig,
+) {
+    let mut service = match proxy {
+        Proxy::None => return,
+        Proxy::Nginx => Service {
+            image: Some("nginx:stable-alpine".to_string()),
+            volumes: vec![
+                format!("./{}:/etc/nginx/conf.d/default.conf:ro", NGINX_FILE),
+                "./proxy/certs:/etc/nginx/certs:ro".to_string(),
+            ],
+            ..Default::default()
+        },
+        Proxy::Traefik => {
+            // Traefik sees every container of the docker host, so it only
+            // routes the ones of this compose project
+            let command = [
+                "--providers.docker=true".to_string(),
+                "--providers.docker.exposedbydefault=false".to_string(),
+                format!(
+                    "--providers.docker.constraints=Label(`com.docker.compose.project`,`{}`)",
+                    project_name
+                ),
+                "--providers.file.filename=/etc/traefik/dynamic.yml".to_string(),
+                "--entrypoints.web.address=:80".to_string(),
+                "--entrypoints.websecure.address=:443".to_string(),
+                format!(
+                    "--entrypoints.web.http.redirections.entrypoint.to=:{}",
+                    https_port
+                ),
+                "--entrypoints.web.http.redirections.entrypoint.scheme=https".to_string(),
+            ];
+            Service {
+                image: Some("traefik:v3.1".to_string()),
+                command: command.to_vec(),
+                volumes: vec![
+                    "/var/run/docker.sock:/var/run/docker.sock:ro".to_string(),
+                    "./proxy:/etc/traefik:ro".to_string(),
+                ],
+                ..Default::default()
+            }
+        }
+    };
+    service.ports = vec![format!("{}:80", http_port), format!("{}:443", https_port)];
+    service.depends_on = BTreeMap::from([(
+        "web".to_string(),
+        DependsOn {
+            condition: "service_started".to_string(),
+        },
+    )]);
+    service.networks = vec![NETWORK.to_string()];
+    service.restart = Some("always".to_string());
+
+    let web = compose.services.get_mut("web").unwrap();
+    web.ports.clear();
+    if proxy == Proxy::Traefik {
+        let router = |name: &str, rule: String, port: u16| {
+            [
+                format!("traefik.http.routers.{}.rule={}", name, rule),
+                format!("traefik.http.routers.{}.entrypoints=websecure", name),
+                format!("traefik.http.routers.{}.tls=true", name),
+                format!("traefik.http.routers.{}.service={}", name, name),
+                format!(
+                    "traefik.http.services.{}.loadbalancer.server.port={}",
+                    name, port
+                ),
+            ]
+        };
+        let host = format!("Host(`{}`)", domain);
+        web.labels = vec!["traefik.enable=true".to_string()];
+        web.labels.extend(router(
+            &format!("{}-web", project_name),
+            host.clone(),
+            HTTP_PORT,
+        ));
+        web.labels.extend(router(
+            &format!("{}-bus", project_name),
+            format!("{} && PathPrefix(`{}`)", host, bus_path(odoo_version)),
+            config.bus_port(),
+        ));
+    }
+    compose.services.insert(proxy.name().to_string(), service);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compose::odoo_stack;
+    use crate::odoo_conf::Preset;
+
+    fn config(preset: Preset) -> OdooConfig {
+        OdooConfig::preset(preset, "admin", "secret")
+    }
+
+    #[test]
+    fn nginx_routes_the_bus_of_the_odoo_version() {
+        let conf = nginx_conf("shop.localhost", 8443, "17", &config(Preset::Staging));
+        assert!(conf.contains("location /websocket {"));
+        assert!(conf.contains("server web:8072;"));
+        assert!(conf.contains("return 301 https://$host:8443$request_uri;"));
+        assert!(!conf.contains("{{"));
+
+        let conf = nginx_conf("shop.localhost", 443, "15", &config(Preset::Staging));
+        assert!(conf.contains("location /longpolling {"));
+        assert!(conf.contains("return 301 https://$host$request_uri;"));
+    }
+
+    #[test]
+    fn bus_goes_to_the_http_port_without_workers() {
+        let conf = nginx_conf("shop.localhost", 8443, "17", &config(Preset::Dev));
+        assert!(conf.contains("server web:8069;"));
+        assert!(!conf.contains("web:8072"));
+
+        let mut compose = odoo_stack("odoo:17", "16", 8069, 8072, None);
+        add_to(
+            &mut compose,
+            Proxy::Traefik,
+            "shop",
+            "shop.localhost",
+            8080,
+            8443,
+            "17",
+            &config(Preset::Dev),
+        );
+        assert!(compose.services["web"]
+            .labels
+            .contains(&"traefik.http.services.shop-bus.loadbalancer.server.port=8069".to_string()));
+    }
+
+    #[test]
+    fn proxy_replaces_the_published_odoo_ports() {
+        let mut compose = odoo_stack("odoo:17", "16", 8069, 8072, None);
+        add_to(
+            &mut compose,
+            Proxy::Nginx,
+            "shop",
+            "shop.localhost",
+            8080,
+            8443,
+            "17",
+            &config(Preset::Staging),
+        );
+        assert!(compose.services["web"].ports.is_empty());
+        assert_eq!(compose.services["nginx"].ports, ["8080:80", "8443:443"]);
+
+        let mut compose = odoo_stack("odoo:17", "16", 8069, 8072, None);
+        add_to(
+            &mut compose,
+            Proxy::Traefik,
+            "shop",
+            "shop.localhost",
+            8080,
+            8443,
+            "17",
+            &config(Preset::Staging),
+        );
+        let labels = &compose.services["web"].labels;
+        assert!(labels.contains(
+            &"traefik.http.routers.shop-bus.rule=Host(`shop.localhost`) && PathPrefix(`/websocket`)"
+                .to_string()
+        ));
+        assert!(labels
+            .contains(&"traefik.http.services.shop-bus.loadbalancer.server.port=8072".to_string()));
+        assert!(compose.services["traefik"]
+            .command
+            .contains(&"--entrypoints.web.http.redirections.entrypoint.to=:8443".to_string()));
+    }
+
+    #[test]
+    fn validates_domains() {
+        assert_eq!(validate_domain("shop.localhost"), Ok(()));
+        for domain in [
+            "",
+            "Shop.localhost",
+            "shop..localhost",
+            "-shop.com",
+            "shop/x",
+        ] {
+            assert!(validate_domain(domain).is_err(), "{}", domain);
+        }
+    }
+
+    #[test]
+    fn default_domains_are_valid() {
+        assert_eq!(default_domain("shop"), "shop.localhost");
+        for name in ["my_shop", "shop_", "shop-2_", "2024_shop"] {
+            assert_eq!(validate_domain(&default_domain(name)), Ok(()), "{}", name);
+        }
+    }
+
+    #[test]
+    fn self_signed_certificates_are_pem() {
+        let certificate = Certificate::self_signed("shop.localhost").unwrap();
+        assert!(certificate.cert.starts_with("-----BEGIN CERTIFICATE-----"));
+        assert!(certificate.key.contains("PRIVATE KEY-----"));
+    }
+}
@@ -0,0 +1,165 @@
+use crate::config::config_dir;
+use crate::project::Project;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Every project generated by the tool, kept in the user's config directory
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Registry {
+    #[serde(default)]
+    pub projects: Vec<RegistryEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RegistryEntry {
+    pub path: PathBuf,
+    pub created: DateTime<Utc>,
+    #[serde(flatten)]
+    pub project: Project,
+}
+
+fn registry_path() -> PathBuf {
+    config_dir().join("projects.yml")
+}
+
+// Absolute path of a project directory, whether it still exists or not
+pub fn absolute_path(project_dir: &Path) -> PathBuf {
+    fs::canonicalize(project_dir)
+        .or_else(|_| std::path::absolute(project_dir))
+        .unwrap_or_else(|_| project_dir.to_path_buf())
+}
+
+impl Registry {
+    pub fn load() -> Result<Registry, String> {
+        let path = registry_path();
+        match fs::read_to_string(&path) {
+            Ok(content) => serde_yaml::from_str(&content)
+                .map_err(|e| format!("Invalid {}: {}", path.display(), e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Registry::default()),
+            Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+        }
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = registry_path();
+        fs::create_dir_all(path.parent().unwrap())
+            .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+        let content = serde_yaml::to_string(self)
+            .map_err(|e| format!("Failed to serialize the registry: {}", e))?;
+        fs::write(&path, content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+
+    pub fn find(&self, project_dir: &Path) -> Option<&RegistryEntry> {
+        let path = absolute_path(project_dir);
+        self.projects.iter().find(|entry| entry.path == path)
+    }
+
+    // Add the project, or refresh it if it is already registered
+    pub fn record(&mut self, project_dir: &Path, project: Project) {
+        let path = absolute_path(project_dir);
+        match self.projects.iter_mut().find(|entry| entry.path == path) {
+            Some(entry) => entry.project = project,
+            None => self.projects.push(RegistryEntry {
+                path,
+                created: Utc::now(),
+                project,
+            }),
+        }
+    }
+
+    pub fn forget(&mut self, project_dir: &Path) -> Option<RegistryEntry> {
+        let path = absolute_path(project_dir);
+        let index = self.projects.iter().position(|entry| entry.path == path)?;
+        Some(self.projects.remove(index))
+    }
+
+    // Ports of every registered project, running or not
+    pub fn claimed_ports(&self) -> Vec<u16> {
+        self.projects
+            .iter()
+            .flat_map(|entry| entry.project.ports())
+            .collect()
+    }
+}
+
+// Record the project in the registry after it was created or changed
+pub fn record(project_dir: &Path, project: &Project) -> Result<(), String> {
+    let mut registry = Registry::load()?;
+    registry.record(project_dir, project.clone());
+    registry.save()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn project(name: &str, port: u16, extra: &str) -> Project {
+        serde_yaml::from_str(&format!(
+            "name: {}\nodoo_version: \"17\"\npostgres_version: \"16\"\nport: {}\n{}",
+            name, port, extra
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn records_finds_and_forgets_projects() {
+        let dir =
+            std::env::temp_dir().join(format!("odoo-automation-registry-{}", std::process::id()));
+        let mut registry = Registry::default();
+        registry.record(
+            &dir.join("shop"),
+            project("shop", 8069, "longpolling_port: 8072"),
+        );
+        registry.record(&dir.join("crm"), project("crm", 8070, ""));
+
+        let entry = registry.find(&dir.join("shop")).unwrap();
+        assert_eq!(entry.path, dir.join("shop"));
+        assert_eq!(entry.project.port, 8069);
+        assert!(registry.find(&dir.join("other")).is_none());
+
+        // Recording again refreshes the entry
+        let created = entry.created;
+        registry.record(&dir.join("shop"), project("shop", 8071, ""));
+        assert_eq!(registry.projects.len(), 2);
+        let entry = registry.find(&dir.join("shop")).unwrap();
+        assert_eq!(entry.project.port, 8071);
+        assert_eq!(entry.created, created);
+
+        assert_eq!(
+            registry.forget(&dir.join("crm")).unwrap().project.name,
+            "crm"
+        );
+        assert!(registry.forget(&dir.join("crm")).is_none());
+        assert_eq!(registry.projects.len(), 1);
+    }
+
+    #[test]
+    fn stopped_projects_keep_their_ports() {
+        let dir =
+            std::env::temp_dir().join(format!("odoo-automation-claimed-{}", std::process::id()));
+        let mut registry = Registry::default();
+        registry.record(
+            &dir.join("shop"),
+            project(
+                "shop",
+                8080,
+                "longpolling_port: 8072\nproxy: nginx\nhttps_port: 8443\nservices:\n  - name: mailpit\n    ports: [8025]\n",
+            ),
+        );
+        registry.record(&dir.join("crm"), project("crm", 8069, ""));
+
+        let mut claimed = registry.claimed_ports();
+        claimed.sort();
+        assert_eq!(claimed, [8025, 8069, 8072, 8080, 8443]);
+        assert!(!claimed.contains(&0));
+        assert_eq!(
+            crate::ports::find_available_port(8069, &claimed).map(|port| claimed.contains(&port)),
+            Ok(false)
+        );
+
+        registry.forget(&dir.join("shop"));
+        assert_eq!(registry.claimed_ports(), [8069]);
+    }
+}
@@ -0,0 +1,150 @@
+use crate::plan::{Action, Plan};
+use std::path::Path;
+
+// Built-in templates of a new addon, with {{placeholders}}
+const MANIFEST: &str = include_str!("../data/scaffold/__manifest__.py");
+const INIT: &str = include_str!("../data/scaffold/__init__.py");
+const ACCESS: &str = include_str!("../data/scaffold/ir.model.access.csv");
+const MODEL: &str = include_str!("../data/scaffold/model.py");
+const VIEWS: &str = include_str!("../data/scaffold/views.xml");
+
+// Addon names are Python package names
+pub fn validate_name(name: &str) -> Result<(), String> {
+    let valid = name.starts_with(|c: char| c.is_ascii_lowercase())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "Invalid addon name '{}': use lowercase letters, digits and '_', starting with a letter",
+            name
+        ))
+    }
+}
+
+// Files and folders of a new addon for `odoo_version`, optionally with a
+// sample model, its views and a menu
+pub fn addon_plan(addon_dir: &Path, name: &str, odoo_version: &str, sample: bool) -> Plan {
+    let major: u32 = odoo_version.parse().unwrap_or(0);
+    let title = name
+        .split('_')
+        .filter(|word| !word.is_empty())
+        .map(|word| word[..1].to_uppercase() + &word[1..])
+        .collect::<Vec<_>>()
+        .join(" ");
+    let model = format!("{}.record", name);
+    let model_id = model.replace('.', "_");
+    let class = title.replace(' ', "") + "Record";
+    let data = if sample {
+        format!("\n        'views/{}_views.xml',", model_id)
+    } else {
+        String::new()
+    };
+    let vars: [(&str, &str); 8] = [
+        ("addon", name),
+        ("title", &title),
+        ("version", &format!("{}.0.1.0.0", odoo_version)),
+        ("model", &model),
+        ("model_id", &model_id),
+        ("class", &class),
+        // Odoo 18 renamed the tree view to list
+        ("list", if major >= 18 { "list" } else { "tree" }),
+        ("data", &data),
+    ];
+    let render = |template: &str| {
+        vars.iter()
+            .fold(template.to_string(), |text, (key, value)| {
+                text.replace(&format!("{{{{{}}}}}", key), value)
+            })
+    };
+
+    let mut plan = Plan::default();
+    for folder in ["models", "views", "security", "i18n"] {
+        plan.push(Action::CreateDir(addon_dir.join(folder)));
+    }
+    plan.write_file(addon_dir.join("__init__.py"), render(INIT));
+    plan.write_file(addon_dir.join("__manifest__.py"), render(MANIFEST));
+    plan.write_file(addon_dir.join("i18n/.gitkeep"), String::new());
+    let mut access = render(ACCESS);
+    if sample {
+        plan.write_file(
+            addon_dir.join("models/__init__.py"),
+            format!("from . import {}\n", model_id),
+        );
+        plan.write_file(
+            addon_dir.join(format!("models/{}.py", model_id)),
+            render(MODEL),
+        );
+        plan.write_file(
+            addon_dir.join(format!("views/{}_views.xml", model_id)),
+            render(VIEWS),
+        );
+        access += &format!(
+            "access_{},{}.user,model_{},base.group_user,1,1,1,1\n",
+            model_id, model, model_id
+        );
+    } else {
+        plan.write_file(addon_dir.join("models/__init__.py"), String::new());
+        plan.write_file(addon_dir.join("views/.gitkeep"), String::new());
+    }
+    plan.write_file(addon_dir.join("security/ir.model.access.csv"), access);
+    plan
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manifest::Manifest;
+
+    fn file<'a>(plan: &'a Plan, path: &str) -> &'a str {
+        plan.actions
+            .iter()
+            .find_map(|action| match action {
+                Action::WriteFile {
+                    path: p, content, ..
+                } if p == Path::new(path) => Some(content.as_str()),
+                _ => None,
+            })
+            .unwrap_or_else(|| panic!("{} is not planned", path))
+    }
+
+    #[test]
+    fn validates_addon_names() {
+        assert_eq!(validate_name("sale_extra2"), Ok(()));
+        for name in ["", "Sale", "2sale", "sale-extra", "_sale", "sale.extra"] {
+            assert!(validate_name(name).is_err(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn manifest_matches_the_odoo_version() {
+        let plan = addon_plan(Path::new("sale_extra"), "sale_extra", "17", false);
+        let manifest = Manifest::parse(file(&plan, "sale_extra/__manifest__.py")).unwrap();
+        assert_eq!(manifest.name, "Sale Extra");
+        assert_eq!(manifest.version.as_deref(), Some("17.0.1.0.0"));
+        assert!(manifest.supports("17"));
+        assert_eq!(manifest.depends, ["base"]);
+        assert_eq!(file(&plan, "sale_extra/models/__init__.py"), "");
+    }
+
+    #[test]
+    fn sample_model_uses_the_right_view_type() {
+        let plan = addon_plan(Path::new("shop"), "shop", "18", true);
+        let views = file(&plan, "shop/views/shop_record_views.xml");
+        assert!(views.contains("<list>"));
+        assert!(views.contains("<field name=\"view_mode\">list,form</field>"));
+        assert!(!views.contains("{{"));
+        assert!(
+            file(&plan, "shop/models/shop_record.py").contains("class ShopRecord(models.Model)")
+        );
+        assert!(file(&plan, "shop/__manifest__.py").contains("'views/shop_record_views.xml',"));
+        assert!(file(&plan, "shop/security/ir.model.access.csv").contains(
+            "access_shop_record,shop.record.user,model_shop_record,base.group_user,1,1,1,1"
+        ));
+
+        let plan = addon_plan(Path::new("shop"), "shop", "16", true);
+        assert!(file(&plan, "shop/views/shop_record_views.xml").contains("<tree>"));
+    }
+}
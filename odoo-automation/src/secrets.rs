@@ -0,0 +1,164 @@
+use crate::docker;
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::Path;
+
+// Length of the generated passwords
+const PASSWORD_LENGTH: usize = 32;
+
+// Per-project passwords, stored in the project's .env file
+pub struct Secrets {
+    pub db_password: String,
+    pub admin_password: String,
+}
+
+impl Secrets {
+    pub fn generate() -> Secrets {
+        Secrets {
+            db_password: random_password(),
+            admin_password: random_password(),
+        }
+    }
+
+    // The passwords of an existing project, when its .env has both
+    pub fn read_env_file(project_dir: &Path) -> Option<Secrets> {
+        let content = fs::read_to_string(project_dir.join(".env")).ok()?;
+        let value = |key: &str| {
+            content.lines().find_map(|line| match line.split_once('=') {
+                Some((k, v)) if k.trim() == key && !v.trim().is_empty() => {
+                    Some(v.trim().to_string())
+                }
+                _ => None,
+            })
+        };
+        Some(Secrets {
+            db_password: value("DB_PASSWORD")?,
+            admin_password: value("ODOO_ADMIN_PASSWORD")?,
+        })
+    }
+
+    // Contents of the .env file read by docker compose
+    pub fn env_file(&self) -> String {
+        format!(
+            "# Generated by odoo-automation, keep this file private\n\
+             DB_PASSWORD={}\n\
+             ODOO_ADMIN_PASSWORD={}\n",
+            self.db_password, self.admin_password
+        )
+    }
+
+    // Write the .env file, readable by the owner only
+    pub fn write_env_file(&self, project_dir: &Path) -> std::io::Result<()> {
+        let content = self.env_file();
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(project_dir.join(".env"))?;
+        file.write_all(content.as_bytes())
+    }
+}
+
+fn random_password() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(PASSWORD_LENGTH)
+        .map(char::from)
+        .collect()
+}
+
+// Replace the value of `key` in an odoo.conf, keeping every other line as-is
+pub fn set_conf_option(content: &str, key: &str, value: &str) -> String {
+    let mut found = false;
+    let mut lines: Vec<String> = content
+        .lines()
+        .map(|line| match line.split_once('=') {
+            Some((k, _)) if k.trim() == key => {
+                found = true;
+                format!("{} = {}", key, value)
+            }
+            _ => line.to_string(),
+        })
+        .collect();
+    if !found {
+        lines.push(format!("{} = {}", key, value));
+    }
+    lines.join("\n") + "\n"
+}
+
+// Generate new passwords for an existing project and apply them everywhere
+pub fn rotate(project_dir: &Path) -> Result<(), String> {
+    let conf_path = project_dir.join("config/odoo.conf");
+    let conf = fs::read_to_string(&conf_path)
+        .map_err(|e| format!("Failed to read {}: {}", conf_path.display(), e))?;
+
+    let secrets = Secrets::generate();
+
+    // The database keeps its own copy of the password once initialized, so
+    // change it there before writing any file. pgdata belongs to the postgres
+    // user of the container, so only its existence can be checked.
+    if project_dir.join("data/pgdata").is_dir() {
+        let sql = format!("ALTER USER odoo WITH PASSWORD '{}'", secrets.db_password);
+        docker::compose(
+            project_dir,
+            &[
+                "exec", "-T", "postgres", "psql", "-U", "odoo", "-d", "postgres", "-c", &sql,
+            ],
+        )
+        .map_err(|e| {
+            format!(
+                "{}: failed to change the database password, is the postgres container running?",
+                e
+            )
+        })?;
+    }
+
+    let conf = set_conf_option(&conf, "db_password", &secrets.db_password);
+    let conf = set_conf_option(&conf, "admin_passwd", &secrets.admin_password);
+    fs::write(&conf_path, conf)
+        .map_err(|e| format!("Failed to write {}: {}", conf_path.display(), e))?;
+    secrets
+        .write_env_file(project_dir)
+        .map_err(|e| format!("Failed to write .env: {}", e))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replaces_or_appends_conf_options() {
+        let conf = "[options]\n; a comment\ndb_password = old\nadmin_passwd=old\n";
+        let conf = set_conf_option(conf, "db_password", "new");
+        let conf = set_conf_option(&conf, "admin_passwd", "secret");
+        let conf = set_conf_option(&conf, "list_db", "False");
+        assert_eq!(
+            conf,
+            "[options]\n; a comment\ndb_password = new\nadmin_passwd = secret\nlist_db = False\n"
+        );
+    }
+
+    #[test]
+    fn reads_both_passwords_from_the_env_file() {
+        let dir = std::env::temp_dir().join(format!("odoo-automation-env-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        assert!(Secrets::read_env_file(&dir).is_none());
+
+        let secrets = Secrets::generate();
+        secrets.write_env_file(&dir).unwrap();
+        let read = Secrets::read_env_file(&dir).unwrap();
+        assert_eq!(read.db_password, secrets.db_password);
+        assert_eq!(read.admin_password, secrets.admin_password);
+
+        // Both passwords are needed
+        fs::write(dir.join(".env"), "DB_PASSWORD=x\nODOO_ADMIN_PASSWORD= \n").unwrap();
+        assert!(Secrets::read_env_file(&dir).is_none());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,261 @@
+use crate::compose::{ComposeFile, Service, NETWORK};
+use crate::config::config_dir;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Component, Path};
+
+// The service templates shipped with the tool
+const EMBEDDED: &str = include_str!("../data/services.yml");
+
+// Services of the generated stack a template can't replace
+const RESERVED: [&str; 5] = ["web", "postgres", "nginx", "traefik", "proxy"];
+
+// Companion services the wizard offers
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Templates {
+    pub services: Vec<ServiceTemplate>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ServiceTemplate {
+    pub name: String,
+    pub description: String,
+    pub image: String,
+    #[serde(default)]
+    pub ports: Vec<TemplatePort>,
+    #[serde(default)]
+    pub environment: BTreeMap<String, String>,
+    #[serde(default)]
+    pub command: Vec<String>,
+    #[serde(default)]
+    pub files: Vec<TemplateFile>,
+    #[serde(default)]
+    pub web_environment: BTreeMap<String, String>,
+    pub smtp_port: Option<u16>,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TemplatePort {
+    pub container: u16,
+    // First host port tried
+    pub host: u16,
+    pub label: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TemplateFile {
+    // Relative to the project directory
+    pub path: String,
+    // Where it is mounted, read-only, in the container
+    pub mount: String,
+    pub content: String,
+}
+
+// A service added to a project, with the host ports it was given
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExtraService {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ports: Vec<u16>,
+}
+
+impl Templates {
+    // The embedded templates, with the user's services.yml on top of them
+    pub fn load() -> Result<Templates, String> {
+        let mut templates = Templates::parse(EMBEDDED).expect("Invalid embedded services.yml");
+        let path = config_dir().join("services.yml");
+        match fs::read_to_string(&path) {
+            Ok(content) => {
+                let overrides = Templates::parse(&content)
+                    .map_err(|e| format!("Invalid {}: {}", path.display(), e))?;
+                templates.merge(overrides);
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+        }
+        Ok(templates)
+    }
+
+    pub fn parse(content: &str) -> Result<Templates, String> {
+        let templates: Templates = serde_yaml::from_str(content).map_err(|e| e.to_string())?;
+        for template in &templates.services {
+            template.validate()?;
+        }
+        Ok(templates)
+    }
+
+    // Templates of `other` replace the ones with the same name
+    fn merge(&mut self, other: Templates) {
+        for template in other.services {
+            match self.services.iter_mut().find(|t| t.name == template.name) {
+                Some(existing) => *existing = template,
+                None => self.services.push(template),
+            }
+        }
+    }
+
+    pub fn find(&self, name: &str) -> Option<&ServiceTemplate> {
+        self.services.iter().find(|template| template.name == name)
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.services
+            .iter()
+            .map(|template| template.name.as_str())
+            .collect()
+    }
+}
+
+impl ServiceTemplate {
+    fn validate(&self) -> Result<(), String> {
+        let valid_name = self.name.starts_with(|c: char| c.is_ascii_lowercase())
+            && self
+                .name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+        if !valid_name {
+            return Err(format!("Invalid service name '{}'", self.name));
+        }
+        if RESERVED.contains(&self.name.as_str()) {
+            return Err(format!(
+                "Service name '{}' is used by the generated stack",
+                self.name
+            ));
+        }
+        for file in &self.files {
+            let inside_project = Path::new(&file.path)
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)));
+            if file.path.is_empty() || !inside_project {
+                return Err(format!(
+                    "Service '{}': file {} must be a path inside the project",
+                    self.name, file.path
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn render(&self, text: &str, project_name: &str) -> String {
+        text.replace("{{project}}", project_name)
+    }
+
+    // Files to write in the project for this service
+    pub fn files(&self, project_name: &str) -> Vec<(String, String)> {
+        self.files
+            .iter()
+            .map(|file| (file.path.clone(), self.render(&file.content, project_name)))
+            .collect()
+    }
+}
+
+// Add the service of `template` to the stack, publishing its ports on
+// `host_ports` (one for each port of the template)
+pub fn add_to(
+    compose: &mut ComposeFile,
+    template: &ServiceTemplate,
+    project_name: &str,
+    host_ports: &[u16],
+) {
+    let render = |vars: &BTreeMap<String, String>| -> BTreeMap<String, String> {
+        vars.iter()
+            .map(|(key, value)| (key.clone(), template.render(value, project_name)))
+            .collect()
+    };
+    let service = Service {
+        image: Some(template.image.clone()),
+        command: template.command.clone(),
+        ports: template
+            .ports
+            .iter()
+            .zip(host_ports)
+            .map(|(port, host)| format!("{}:{}", host, port.container))
+            .collect(),
+        volumes: template
+            .files
+            .iter()
+            .map(|file| format!("./{}:{}:ro", file.path, file.mount))
+            .collect(),
+        environment: render(&template.environment),
+        networks: vec![NETWORK.to_string()],
+        restart: Some("always".to_string()),
+        ..Default::default()
+    };
+    compose.services.insert(template.name.clone(), service);
+
+    let web = compose.services.get_mut("web").unwrap();
+    web.environment.extend(render(&template.web_environment));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compose::odoo_stack;
+
+    #[test]
+    fn embedded_templates_are_valid() {
+        let templates = Templates::parse(EMBEDDED).unwrap();
+        assert_eq!(templates.names(), ["mailpit", "pgadmin", "redis"]);
+        assert_eq!(templates.find("mailpit").unwrap().smtp_port, Some(1025));
+    }
+
+    #[test]
+    fn templates_become_services() {
+        let templates = Templates::parse(EMBEDDED).unwrap();
+        let mut compose = odoo_stack("odoo:17", "16", 8069, 8072, None);
+        add_to(
+            &mut compose,
+            templates.find("pgadmin").unwrap(),
+            "shop",
+            &[5051],
+        );
+        add_to(&mut compose, templates.find("redis").unwrap(), "shop", &[]);
+
+        let pgadmin = &compose.services["pgadmin"];
+        assert_eq!(pgadmin.ports, ["5051:80"]);
+        assert_eq!(
+            pgadmin.volumes,
+            ["./config/pgadmin-servers.json:/pgadmin4/servers.json:ro"]
+        );
+        let files = templates.find("pgadmin").unwrap().files("shop");
+        assert!(files[0].1.contains("\"Name\": \"shop\""));
+
+        assert!(compose.services["redis"].ports.is_empty());
+        let web = &compose.services["web"];
+        assert_eq!(web.environment["ODOO_SESSION_REDIS_PREFIX"], "shop");
+        assert_eq!(web.environment["HOST"], "postgres");
+    }
+
+    #[test]
+    fn rejects_unsafe_templates() {
+        let template = |name: &str, path: &str| {
+            format!(
+                "services:\n  - name: {}\n    description: x\n    image: x\n    files:\n      - {{ path: \"{}\", mount: /x, content: x }}\n",
+                name, path
+            )
+        };
+        assert!(Templates::parse(&template("adminer", "config/x")).is_ok());
+        assert!(Templates::parse(&template("web", "config/x")).is_err());
+        assert!(Templates::parse(&template("adminer", "../x")).is_err());
+        assert!(Templates::parse(&template("adminer", "/etc/x")).is_err());
+    }
+
+    #[test]
+    fn user_templates_replace_embedded_ones() {
+        let mut templates = Templates::parse(EMBEDDED).unwrap();
+        templates.merge(
+            Templates::parse(
+                "services:\n  - name: redis\n    description: Valkey\n    image: valkey/valkey:8\n",
+            )
+            .unwrap(),
+        );
+        assert_eq!(templates.find("redis").unwrap().image, "valkey/valkey:8");
+        assert_eq!(templates.services.len(), 3);
+    }
+}
@@ -0,0 +1,217 @@
+use crate::ask_yes_no;
+use crate::config::{cache_dir, expand_home, home_dir};
+use crate::git::{self, GitError};
+use serde::Deserialize;
+use std::path::PathBuf;
+
+// Bigbang Odoo Addons Pack, the default source
+const BBG_PACK_URL: &str = "https://github.com/bbgstack/BBG-ODOO-ADDONS-PACK";
+
+// Branch naming used when a git source doesn't set one
+const DEFAULT_BRANCH: &str = "{version}.0";
+
+// A place addons are taken from: a git repository cloned locally, or a local
+// directory. `{version}` in the branch is replaced with the Odoo version.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Source {
+    pub name: String,
+    pub git: Option<String>,
+    pub path: Option<String>,
+    pub branch: Option<String>,
+    // Where a git source is cloned, defaults to the cache directory
+    pub checkout: Option<String>,
+}
+
+impl Source {
+    pub fn bbg_pack() -> Source {
+        Source {
+            name: "bbg".to_string(),
+            git: Some(BBG_PACK_URL.to_string()),
+            path: None,
+            branch: None,
+            checkout: Some(
+                home_dir()
+                    .join("Documents/BBG-ODOO-ADDONS-PACK")
+                    .to_string_lossy()
+                    .into_owned(),
+            ),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        match (&self.git, &self.path) {
+            (Some(_), None) | (None, Some(_)) => Ok(()),
+            _ => Err(format!(
+                "source '{}' needs exactly one of `git` or `path`",
+                self.name
+            )),
+        }
+    }
+
+    pub fn branch_for(&self, odoo_version: &str) -> Option<String> {
+        let pattern = match (&self.branch, &self.git) {
+            (Some(branch), _) => branch.as_str(),
+            (None, Some(_)) => DEFAULT_BRANCH,
+            (None, None) => return None,
+        };
+        Some(pattern.replace("{version}", odoo_version))
+    }
+
+    // Directory holding the addons of this source
+    pub fn local_path(&self) -> PathBuf {
+        match (&self.path, &self.checkout) {
+            (Some(path), _) | (None, Some(path)) => expand_home(path),
+            (None, None) => cache_dir().join("sources").join(&self.name),
+        }
+    }
+
+    // Whether the directory on disk already is what `sync` would check out,
+    // for dry runs which must not clone or switch branches
+    pub fn check_synced(&self, odoo_version: &str) -> Result<(), String> {
+        let repo_path = self.local_path();
+        if !repo_path.is_dir() {
+            return Err(format!(
+                "'{}' is not cloned to {} yet",
+                self.name,
+                repo_path.display()
+            ));
+        }
+        if let Some(branch) = self.branch_for(odoo_version) {
+            let repo =
+                git::open(&repo_path).map_err(|e| format!("Source '{}': {}", self.name, e))?;
+            if git::current_branch(&repo).as_deref() != Some(branch.as_str()) {
+                return Err(format!("'{}' is not on branch {}", self.name, branch));
+            }
+        }
+        Ok(())
+    }
+
+    // Clone the source, or switch the existing checkout to the branch of
+    // `odoo_version` and optionally pull it. Returns the local path.
+    pub fn sync(&self, odoo_version: &str, pull: Option<bool>) -> Result<PathBuf, String> {
+        let repo_path = self.local_path();
+        let branch = self.branch_for(odoo_version);
+        let error = |e: GitError| format!("Source '{}': {}", self.name, e);
+
+        let Some(url) = &self.git else {
+            // Local directory, possibly a checkout to switch to the right branch
+            if !repo_path.is_dir() {
+                return Err(format!(
+                    "Source '{}': {} is not a directory",
+                    self.name,
+                    repo_path.display()
+                ));
+            }
+            if let Some(branch) = &branch {
+                let repo = git::open(&repo_path).map_err(error)?;
+                git::checkout(&repo, branch).map_err(error)?;
+                println!("Switched '{}' to branch: {}", self.name, branch);
+            }
+            return Ok(repo_path);
+        };
+        let branch = branch.unwrap();
+
+        // Clone the repository if it doesn't exist
+        if repo_path.exists() {
+            println!("Addons of '{}' already exist!", self.name);
+            let repo = git::open(&repo_path).map_err(error)?;
+
+            // chek if want to pull the latest changes
+            let pull_latest: bool = pull.unwrap_or_else(|| {
+                ask_yes_no(&format!(
+                    "Do you want to pull the latest changes of '{}'?",
+                    self.name
+                ))
+            });
+            if pull_latest || !git::has_branch(&repo, &branch) {
+                git::fetch(&repo).map_err(error)?;
+            }
+
+            //  checkout the correct branch and pull the latest changes
+            git::checkout(&repo, &branch).map_err(error)?;
+            println!("Switched to branch: {}", branch);
+            if pull_latest {
+                if git::pull(&repo, &branch).map_err(error)? {
+                    println!("Repository updated successfully!");
+                } else {
+                    println!("Repository is already up to date.");
+                }
+            }
+        } else {
+            println!("Cloning '{}' from {}...", self.name, url);
+            git::clone(url, &repo_path, &branch).map_err(error)?;
+            println!("Repository cloned successfully!");
+        }
+        Ok(repo_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::tests::origin;
+
+    fn source(dir: &std::path::Path) -> Source {
+        Source {
+            name: "test".to_string(),
+            git: Some(dir.join("origin.git").to_string_lossy().into_owned()),
+            path: None,
+            branch: None,
+            checkout: Some(dir.join("checkout").to_string_lossy().into_owned()),
+        }
+    }
+
+    #[test]
+    fn clones_the_branch_of_the_odoo_version() {
+        let dir = std::env::temp_dir().join(format!("odoo-automation-sync-{}", std::process::id()));
+        origin(&dir);
+        let source = source(&dir);
+
+        let path = source.sync("17", Some(false)).unwrap();
+        assert_eq!(path, dir.join("checkout"));
+        assert_eq!(
+            std::fs::read_to_string(path.join("addons.txt")).unwrap(),
+            "17.0 addons"
+        );
+
+        // The existing checkout is switched to the other version
+        source.sync("16", Some(false)).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(path.join("addons.txt")).unwrap(),
+            "16.0 addons"
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn missing_branch_aborts_the_sync() {
+        let dir =
+            std::env::temp_dir().join(format!("odoo-automation-nobranch-{}", std::process::id()));
+        origin(&dir);
+        let source = source(&dir);
+
+        let error = source.sync("15", Some(false)).unwrap_err();
+        assert!(error.contains("branch 15.0 does not exist"), "{}", error);
+        assert!(!dir.join("checkout").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn dry_runs_check_the_checkout_without_syncing() {
+        let dir =
+            std::env::temp_dir().join(format!("odoo-automation-synced-{}", std::process::id()));
+        origin(&dir);
+        let source = source(&dir);
+
+        let error = source.check_synced("17").unwrap_err();
+        assert!(error.contains("is not cloned"), "{}", error);
+        source.sync("17", Some(false)).unwrap();
+        assert_eq!(source.check_synced("17"), Ok(()));
+        assert_eq!(
+            source.check_synced("16"),
+            Err("'test' is not on branch 16.0".to_string())
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
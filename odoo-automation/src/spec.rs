@@ -0,0 +1,148 @@
+use crate::odoo_conf::{Preset, Smtp};
+use crate::project::Edition;
+use crate::proxy::Proxy;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::fs;
+use std::path::{Component, Path};
+
+// Answers to the wizard questions, read from a YAML file or the command line.
+// Every field is optional: missing values are asked interactively.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProjectSpec {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(
+        deserialize_with = "version_from_yaml",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub odoo_version: Option<String>,
+    // PostgreSQL image tag, e.g. 16 or "16.4"
+    #[serde(
+        deserialize_with = "postgres_from_yaml",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub postgres_version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preset: Option<Preset>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub edition: Option<Edition>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enterprise_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<Proxy>,
+    // Host name served by the proxy, e.g. shop.localhost
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub domain: Option<String>,
+    // SMTP server written to odoo.conf, only settable from a spec file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub smtp: Option<Smtp>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub addons: Option<Vec<String>>,
+    // Companion services, by template name
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub services: Option<Vec<String>>,
+    // Folders created in the project besides the ones mounted in the
+    // containers, e.g. docs or scripts
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub folders: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pull_addons: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_docker: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub world_writable: Option<bool>,
+}
+
+impl ProjectSpec {
+    pub fn from_file(path: &str) -> Result<ProjectSpec, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read spec file {}: {}", path, e))?;
+        serde_yaml::from_str(&content).map_err(|e| format!("Invalid spec file {}: {}", path, e))
+    }
+
+    // Values set in `other` take precedence over the ones in `self`
+    pub fn merge(self, other: ProjectSpec) -> ProjectSpec {
+        ProjectSpec {
+            name: other.name.or(self.name),
+            odoo_version: other.odoo_version.or(self.odoo_version),
+            postgres_version: other.postgres_version.or(self.postgres_version),
+            port: other.port.or(self.port),
+            preset: other.preset.or(self.preset),
+            edition: other.edition.or(self.edition),
+            enterprise_path: other.enterprise_path.or(self.enterprise_path),
+            proxy: other.proxy.or(self.proxy),
+            domain: other.domain.or(self.domain),
+            smtp: other.smtp.or(self.smtp),
+            addons: other.addons.or(self.addons),
+            services: other.services.or(self.services),
+            folders: other.folders.or(self.folders),
+            pull_addons: other.pull_addons.or(self.pull_addons),
+            start_docker: other.start_docker.or(self.start_docker),
+            world_writable: other.world_writable.or(self.world_writable),
+        }
+    }
+}
+
+// Extra folders must stay inside the project
+pub fn validate_folder(folder: &str) -> Result<(), String> {
+    let inside_project = Path::new(folder)
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)));
+    if folder.is_empty() || !inside_project {
+        return Err(format!(
+            "Invalid folder '{}': it must be a relative path inside the project",
+            folder
+        ));
+    }
+    Ok(())
+}
+
+// "17", "17.0" and 17 all mean Odoo 17
+pub fn normalize_version(version: &str) -> String {
+    let version = version.trim();
+    version.strip_suffix(".0").unwrap_or(version).to_string()
+}
+
+// YAML turns `odoo_version: 17` into a number, so accept both numbers and strings
+fn version_from_yaml<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value: Option<serde_yaml::Value> = Option::deserialize(deserializer)?;
+    match value {
+        None | Some(serde_yaml::Value::Null) => Ok(None),
+        Some(serde_yaml::Value::String(s)) => Ok(Some(normalize_version(&s))),
+        Some(serde_yaml::Value::Number(n)) => Ok(Some(normalize_version(&n.to_string()))),
+        Some(_) => Err(serde::de::Error::custom(
+            "odoo_version must be a version like 17 or \"17.0\"",
+        )),
+    }
+}
+
+// The same for versions that can't be left out
+pub fn version_from_yaml_required<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    version_from_yaml(deserializer)?
+        .ok_or_else(|| serde::de::Error::custom("the Odoo version is missing"))
+}
+
+// Like the Odoo version, YAML reads `postgres_version: 16` as a number
+fn postgres_from_yaml<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value: Option<serde_yaml::Value> = Option::deserialize(deserializer)?;
+    match value {
+        None | Some(serde_yaml::Value::Null) => Ok(None),
+        Some(serde_yaml::Value::String(s)) => Ok(Some(s.trim().to_string())),
+        Some(serde_yaml::Value::Number(n)) => Ok(Some(n.to_string())),
+        Some(_) => Err(serde::de::Error::custom(
+            "postgres_version must be a version like 16 or \"16.4\"",
+        )),
+    }
+}
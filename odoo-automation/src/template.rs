@@ -0,0 +1,134 @@
+use crate::config::config_dir;
+use crate::spec::ProjectSpec;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+// Saved wizard answers, one YAML file per template
+pub fn templates_dir() -> PathBuf {
+    config_dir().join("templates")
+}
+
+pub fn validate_name(name: &str) -> Result<(), String> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "Invalid template name '{}': use letters, digits, '-' and '_'",
+            name
+        ))
+    }
+}
+
+fn path(name: &str) -> PathBuf {
+    templates_dir().join(format!("{}.yml", name))
+}
+
+// Names of the saved templates, sorted
+pub fn names() -> Result<Vec<String>, String> {
+    let dir = templates_dir();
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Failed to read {}: {}", dir.display(), e)),
+    };
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            if path.extension()? != "yml" {
+                return None;
+            }
+            Some(path.file_stem()?.to_string_lossy().into_owned())
+        })
+        .collect();
+    names.sort();
+    Ok(names)
+}
+
+pub fn load(name: &str) -> Result<ProjectSpec, String> {
+    validate_name(name)?;
+    let path = path(name);
+    if !path.exists() {
+        let available = names()?;
+        return Err(format!(
+            "Template '{}' not found in {} ({})",
+            name,
+            templates_dir().display(),
+            if available.is_empty() {
+                "there are no templates yet, save one with `new --save-template <name>`".to_string()
+            } else {
+                format!("available: {}", available.join(", "))
+            }
+        ));
+    }
+    ProjectSpec::from_file(&path.to_string_lossy())
+}
+
+// The answers as a template, without the ones that only make sense for one
+// project
+fn render(answers: ProjectSpec) -> Result<String, String> {
+    let template = ProjectSpec {
+        name: None,
+        port: None,
+        domain: None,
+        ..answers
+    };
+    serde_yaml::to_string(&template).map_err(|e| e.to_string())
+}
+
+pub fn save(name: &str, answers: ProjectSpec) -> Result<PathBuf, String> {
+    validate_name(name)?;
+    let content =
+        render(answers).map_err(|e| format!("Failed to serialize template {}: {}", name, e))?;
+    let dir = templates_dir();
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let path = path(name);
+    fs::write(&path, content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::odoo_conf::Preset;
+
+    #[test]
+    fn templates_keep_the_reusable_answers() {
+        let yaml = render(ProjectSpec {
+            name: Some("shop".to_string()),
+            odoo_version: Some("17".to_string()),
+            port: Some(8070),
+            preset: Some(Preset::Staging),
+            domain: Some("shop.localhost".to_string()),
+            addons: Some(vec!["sale_extra".to_string()]),
+            services: Some(Vec::new()),
+            folders: Some(vec!["docs".to_string()]),
+            start_docker: Some(false),
+            ..Default::default()
+        })
+        .unwrap();
+        assert!(!yaml.contains("shop"));
+        assert!(!yaml.contains("8070"));
+        assert!(!yaml.contains("null"));
+
+        let template: ProjectSpec = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(template.odoo_version.as_deref(), Some("17"));
+        assert_eq!(template.preset, Some(Preset::Staging));
+        assert_eq!(template.addons, Some(vec!["sale_extra".to_string()]));
+        assert_eq!(template.services, Some(Vec::new()));
+        assert_eq!(template.folders, Some(vec!["docs".to_string()]));
+        assert_eq!(template.start_docker, Some(false));
+    }
+
+    #[test]
+    fn validates_template_names() {
+        assert_eq!(validate_name("shop-17_dev"), Ok(()));
+        for name in ["", "../shop", "shop.yml", "my shop"] {
+            assert!(validate_name(name).is_err(), "{}", name);
+        }
+    }
+}
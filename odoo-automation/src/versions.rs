@@ -0,0 +1,196 @@
+use crate::config::config_dir;
+use crate::spec::normalize_version;
+use serde::Deserialize;
+use std::fs;
+
+// The compatibility table shipped with the tool
+const EMBEDDED: &str = include_str!("../data/versions.yml");
+
+// Odoo releases the tool can generate projects for
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Versions {
+    pub releases: Vec<Release>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Release {
+    #[serde(deserialize_with = "crate::spec::version_from_yaml_required")]
+    pub odoo: String,
+    pub image: String,
+    pub python: String,
+    pub postgres: PostgresRange,
+    #[serde(default)]
+    pub enterprise: bool,
+    // The image's pip refuses to change the distribution's Python unless told
+    #[serde(default)]
+    pub pip_break_system_packages: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PostgresRange {
+    pub min: u32,
+    pub max: u32,
+    // Image tag used unless another version is asked for
+    pub default: String,
+}
+
+impl PostgresRange {
+    // Whether a postgres image tag such as "16" or "16.4" is in the range
+    pub fn allows(&self, version: &str) -> bool {
+        match version.split('.').next().map(str::parse::<u32>) {
+            Some(Ok(major)) => (self.min..=self.max).contains(&major),
+            _ => false,
+        }
+    }
+}
+
+impl Versions {
+    // The embedded table, with the user's versions.yml on top of it
+    pub fn load() -> Result<Versions, String> {
+        let mut versions = Versions::parse(EMBEDDED).expect("Invalid embedded versions.yml");
+        let path = config_dir().join("versions.yml");
+        match fs::read_to_string(&path) {
+            Ok(content) => {
+                let overrides = Versions::parse(&content)
+                    .map_err(|e| format!("Invalid {}: {}", path.display(), e))?;
+                versions.merge(overrides);
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+        }
+        Ok(versions)
+    }
+
+    pub fn parse(content: &str) -> Result<Versions, String> {
+        let versions: Versions = serde_yaml::from_str(content).map_err(|e| e.to_string())?;
+        for release in &versions.releases {
+            release.validate()?;
+        }
+        Ok(versions)
+    }
+
+    // Releases of `other` replace the ones with the same version; the result
+    // is ordered newest first
+    fn merge(&mut self, other: Versions) {
+        for release in other.releases {
+            match self.releases.iter_mut().find(|r| r.odoo == release.odoo) {
+                Some(existing) => *existing = release,
+                None => self.releases.push(release),
+            }
+        }
+        self.releases
+            .sort_by_key(|release| std::cmp::Reverse(release.odoo.parse::<u32>().unwrap_or(0)));
+    }
+
+    pub fn find(&self, odoo_version: &str) -> Option<&Release> {
+        let odoo_version = normalize_version(odoo_version);
+        self.releases
+            .iter()
+            .find(|release| release.odoo == odoo_version)
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.releases
+            .iter()
+            .map(|release| release.odoo.as_str())
+            .collect()
+    }
+}
+
+impl Release {
+    fn validate(&self) -> Result<(), String> {
+        if self.odoo.parse::<u32>().is_err() {
+            return Err(format!("Odoo version '{}' is not a number", self.odoo));
+        }
+        let postgres = &self.postgres;
+        if postgres.min > postgres.max {
+            return Err(format!(
+                "Odoo {}: postgres min {} is above max {}",
+                self.odoo, postgres.min, postgres.max
+            ));
+        }
+        if !postgres.allows(&postgres.default) {
+            return Err(format!(
+                "Odoo {}: default postgres {} is not between {} and {}",
+                self.odoo, postgres.default, postgres.min, postgres.max
+            ));
+        }
+        Ok(())
+    }
+
+    // One line summary shown in the wizard
+    pub fn describe(&self) -> String {
+        format!(
+            "{} (PostgreSQL {}-{}, Python {}{})",
+            self.odoo,
+            self.postgres.min,
+            self.postgres.max,
+            self.python,
+            if self.enterprise { ", Enterprise" } else { "" }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn embedded_table_is_valid() {
+        let versions = Versions::parse(EMBEDDED).unwrap();
+        assert_eq!(versions.names(), ["18", "17", "16", "15", "14", "13"]);
+        assert_eq!(versions.find("17.0").unwrap().postgres.default, "16");
+    }
+
+    #[test]
+    fn overrides_replace_and_add_releases() {
+        let mut versions = Versions::parse(EMBEDDED).unwrap();
+        let overrides = Versions::parse(
+            "releases:
+              - odoo: 19
+                image: odoo:19
+                python: '3.12'
+                postgres: { min: 13, max: 17, default: '17' }
+              - odoo: '17'
+                image: odoo:17
+                python: '3.10'
+                postgres: { min: 12, max: 16, default: '16.4' }
+                enterprise: true",
+        )
+        .unwrap();
+        versions.merge(overrides);
+
+        assert_eq!(versions.names()[..3], ["19", "18", "17"]);
+        assert!(!versions.find("19").unwrap().enterprise);
+        assert_eq!(versions.find("17").unwrap().postgres.default, "16.4");
+    }
+
+    #[test]
+    fn postgres_range_checks_the_major_version() {
+        let range = PostgresRange {
+            min: 12,
+            max: 16,
+            default: "16".to_string(),
+        };
+        assert!(range.allows("12"));
+        assert!(range.allows("16.4"));
+        assert!(!range.allows("17"));
+        assert!(!range.allows("latest"));
+    }
+
+    #[test]
+    fn rejects_inconsistent_releases() {
+        let error = Versions::parse(
+            "releases:
+              - odoo: '17'
+                image: odoo:17
+                python: '3.10'
+                postgres: { min: 12, max: 16, default: '17' }",
+        )
+        .unwrap_err();
+        assert!(error.contains("not between"), "{}", error);
+    }
+}